@@ -0,0 +1,202 @@
+//! A native client for `tanks_server`, for programs that play or test the game outside of a browser
+//!
+//! ```no_run
+//! # async fn play() -> Result<(), tanks_client::ClientError> {
+//! use futures_util::StreamExt;
+//! use tanks_client::Connection;
+//!
+//! let connection = Connection::connect("ws://localhost:8080", "player").await?;
+//! let (sender, mut events) = connection.split();
+//!
+//! sender.create_session().await?;
+//! while let Some(event) = events.next().await {
+//!     println!("{:?}", event?);
+//!     sender.shoot().await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures_util::{stream::SplitSink, SinkExt, Stream, StreamExt};
+use tanks_core::utils::Vector2;
+use tanks_events::{ClientEvent, ServerEvent};
+use tokio::{net::TcpStream, sync::Mutex};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+/// Path of the websocket endpoint that players connect to
+pub const WEBSOCKET_PATH: &str = "api/ws";
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The connection could not be made or was lost
+    WebSocket(tungstenite::Error),
+    /// The server sent a message that is not a [`ServerEvent`]
+    Malformed(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::WebSocket(e) => write!(f, "websocket error: {}", e),
+            ClientError::Malformed(e) => write!(f, "malformed server event: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<tungstenite::Error> for ClientError {
+    fn from(e: tungstenite::Error) -> Self {
+        ClientError::WebSocket(e)
+    }
+}
+
+/// A player connected to a server, which can be split to send and receive from separate tasks
+pub struct Connection {
+    sender: EventSender,
+    events: EventStream,
+}
+
+impl Connection {
+    /// Connect to the server at an address such as `ws://localhost:8080`, playing under the given name
+    ///
+    /// The server refuses names that are already connected.
+    pub async fn connect(address: &str, name: &str) -> Result<Self, ClientError> {
+        let uri = format!(
+            "{}/{}?id={}",
+            address.trim_end_matches('/'),
+            WEBSOCKET_PATH,
+            urlencoding::encode(name)
+        );
+        let (socket, _) = connect_async(uri).await?;
+
+        Ok(Self::from_socket(name.to_string(), socket))
+    }
+
+    fn from_socket(id: String, socket: Socket) -> Self {
+        let (sink, stream) = socket.split();
+
+        Self {
+            sender: EventSender {
+                id,
+                sink: Arc::new(Mutex::new(sink)),
+            },
+            events: EventStream {
+                inner: Box::pin(stream),
+            },
+        }
+    }
+
+    /// Name that the connection is playing under
+    pub fn id(&self) -> &str {
+        &self.sender.id
+    }
+
+    pub fn sender(&self) -> &EventSender {
+        &self.sender
+    }
+
+    /// Wait for the next event from the server, or [`None`] once the connection is closed
+    pub async fn next_event(&mut self) -> Option<Result<ServerEvent, ClientError>> {
+        self.events.next().await
+    }
+
+    pub fn split(self) -> (EventSender, EventStream) {
+        (self.sender, self.events)
+    }
+}
+
+/// Sending half of a [`Connection`], which can be cloned to send from several tasks
+#[derive(Clone)]
+pub struct EventSender {
+    id: String,
+    sink: Arc<Mutex<SplitSink<Socket, Message>>>,
+}
+
+impl EventSender {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub async fn send(&self, event: &ClientEvent) -> Result<(), ClientError> {
+        let text = serde_json::to_string(event).unwrap();
+        self.sink.lock().await.send(Message::Text(text)).await?;
+        Ok(())
+    }
+
+    /// Drive the Tank in a direction, where a zero vector stops it
+    pub async fn move_towards(&self, direction: Vector2) -> Result<(), ClientError> {
+        self.send(&ClientEvent::MovementUpdate { direction }).await
+    }
+
+    pub async fn aim(&self, angle: f64) -> Result<(), ClientError> {
+        self.send(&ClientEvent::AimUpdate { angle }).await
+    }
+
+    pub async fn shoot(&self) -> Result<(), ClientError> {
+        self.send(&ClientEvent::Shoot).await
+    }
+
+    pub async fn join_session(&self, session_id: &str) -> Result<(), ClientError> {
+        self.send(&ClientEvent::JoinSession(session_id.to_string()))
+            .await
+    }
+
+    pub async fn create_session(&self) -> Result<(), ClientError> {
+        self.send(&ClientEvent::CreateSession).await
+    }
+
+    pub async fn leave_session(&self) -> Result<(), ClientError> {
+        self.send(&ClientEvent::LeaveSession).await
+    }
+
+    /// Close the connection, which also leaves the session
+    pub async fn close(&self) -> Result<(), ClientError> {
+        self.sink.lock().await.close().await?;
+        Ok(())
+    }
+}
+
+/// Receiving half of a [`Connection`], a stream of the events sent by the server
+pub struct EventStream {
+    inner: Pin<Box<dyn Stream<Item = Result<Message, tungstenite::Error>> + Send>>,
+}
+
+impl Stream for EventStream {
+    type Item = Result<ServerEvent, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e.into()))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            // pings are answered by the socket itself, so only events are passed on
+            match message {
+                Message::Text(text) => {
+                    let event = serde_json::from_str::<ServerEvent>(&text)
+                        .map_err(|e| ClientError::Malformed(e.to_string()));
+                    return Poll::Ready(Some(event));
+                }
+                Message::Close(_) => return Poll::Ready(None),
+                _ => continue,
+            }
+        }
+    }
+}
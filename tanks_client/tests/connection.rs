@@ -0,0 +1,69 @@
+use futures_util::{SinkExt, StreamExt};
+use tanks_client::Connection;
+use tanks_core::utils::Vector2;
+use tanks_events::{ClientEvent, ServerEvent};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Stands in for the server, sending a single event and returning the path that was
+/// connected to along with every event received until the client disconnects
+async fn serve(listener: TcpListener, event: ServerEvent) -> (String, Vec<ClientEvent>) {
+    let (stream, _) = listener.accept().await.unwrap();
+
+    // the request line of the handshake holds the path
+    let mut request = [0; 1024];
+    let read = stream.peek(&mut request).await.unwrap();
+    let path = String::from_utf8_lossy(&request[..read])
+        .split_whitespace()
+        .nth(1)
+        .unwrap()
+        .to_string();
+
+    let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+    // the client should skip over anything other than events
+    socket.send(Message::Ping(Vec::new())).await.unwrap();
+    socket
+        .send(Message::Text(serde_json::to_string(&event).unwrap()))
+        .await
+        .unwrap();
+
+    let mut received = Vec::new();
+    while let Some(Ok(message)) = socket.next().await {
+        if let Message::Text(text) = message {
+            received.push(serde_json::from_str(&text).unwrap());
+        }
+    }
+
+    (path, received)
+}
+
+#[tokio::test]
+async fn events_are_exchanged_as_typed_messages() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("ws://{}/", listener.local_addr().unwrap());
+    let server = tokio::spawn(serve(listener, ServerEvent::MapSeed(42)));
+
+    let mut connection = Connection::connect(&address, "tank one").await.unwrap();
+    assert_eq!(connection.id(), "tank one");
+
+    let event = connection.next_event().await.unwrap().unwrap();
+    assert!(matches!(event, ServerEvent::MapSeed(42)));
+
+    let (sender, _events) = connection.split();
+    sender.join_session("abc").await.unwrap();
+    sender.move_towards(Vector2::new(1.0, 0.0)).await.unwrap();
+    sender.shoot().await.unwrap();
+    sender.close().await.unwrap();
+
+    let (path, received) = server.await.unwrap();
+    assert_eq!(path, "/api/ws?id=tank%20one");
+    assert!(matches!(
+        received.as_slice(),
+        [
+            ClientEvent::JoinSession(session),
+            ClientEvent::MovementUpdate { direction },
+            ClientEvent::Shoot,
+        ] if session == "abc" && direction.x == 1.0
+    ));
+}
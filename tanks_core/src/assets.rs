@@ -1,6 +1,12 @@
-use std::{collections::HashMap, str::Utf8Error};
+use std::{collections::HashMap, fmt, str::Utf8Error};
 
-use crate::common::environment::{Environment, Tile};
+use crate::common::{
+    bot::BotArchetype,
+    environment::{EnemyPlacement, Environment, MapMode, Pickup, PickupKind, Tile},
+};
+
+/// The newest version of the MapData format
+pub const MAP_FORMAT_VERSION: u32 = 2;
 
 /// Character to denote the start and end of a map in a MapData file
 ///
@@ -8,69 +14,436 @@ use crate::common::environment::{Environment, Tile};
 ///
 /// file contents:
 /// ```plaintext
+/// !version 2
+/// # comments can go between maps
 /// @MAP_NAME
+/// :author ndbaker1
+/// :players 2
+/// :mode versus
 /// ..........
-/// ..x11.x1..
-/// ..........
+/// .Ax11.x1B.
+/// .p.r....p.
 /// ..........
+/// *...+....*
 /// @
 /// ```
 ///
 /// you can have multiple definitions of a map in a single map file
+///
+/// | symbol        | meaning                                           |
+/// |---------------|---------------------------------------------------|
+/// | `.` or space  | open ground                                       |
+/// | `x`           | [`Tile::Empty`]                                   |
+/// | `o`           | [`Tile::Hole`]                                    |
+/// | `m`, `w`      | [`Tile::Mud`] and [`Tile::Water`]                 |
+/// | `i`           | [`Tile::Ice`]                                     |
+/// | `T`-`W`       | [`Tile::Teleporter`] paired with the same symbol  |
+/// | `1`-`5`       | indestructable wall of that elevation             |
+/// | `6`-`9`       | destructable wall of elevation 1-4                |
+/// | `p`           | spawn point for a player                          |
+/// | `A`-`D`       | tile in the zone of team 0-3                      |
+/// | `*`           | spawner where waves of bots enter the map         |
+/// | `r`, `g`, `h` | enemy placement of a [`BotArchetype`]             |
+/// | `+`, `>`, `$` | [`PickupKind`] ammo, speed and shield             |
+///
+/// Files without a `!version` line are read as version 1, which predates metadata
+/// and quietly ignores any symbol or line that it does not understand.
+/// From version 2 onwards malformed input, including rows that are not as wide as the
+/// first row of their map, is reported as a [`MapParseError`].
+///
+/// When a name is defined more than once the last definition is kept.
 pub fn parse_environments_file(
     map_bytes: &[u8],
-) -> Result<HashMap<String, Environment>, Utf8Error> {
+) -> Result<HashMap<String, Environment>, MapParseError> {
+    Ok(parse_environments_list(map_bytes)?.into_iter().collect())
+}
+
+/// Same as [`parse_environments_file`], but keeps every definition in the order of the file
+pub fn parse_environments_list(
+    map_bytes: &[u8],
+) -> Result<Vec<(String, Environment)>, MapParseError> {
     const MAP_DELIMITER: char = '@';
+    const VERSION_PREFIX: &str = "!version";
+    const METADATA_PREFIX: char = ':';
+    const COMMENT_PREFIX: char = '#';
 
-    let mut maps = HashMap::new();
+    let text =
+        std::str::from_utf8(map_bytes).map_err(|e| MapParseError::from_utf8(map_bytes, e))?;
 
-    let mut reading = false;
-    let mut relative_row = 0;
-    let mut current_environment_entry = (String::default(), Environment::default());
+    let mut maps = Vec::new();
+    let mut version = 1;
 
-    for (row, line) in std::str::from_utf8(map_bytes)?.lines().enumerate() {
-        if line.starts_with(MAP_DELIMITER) {
-            if reading {
-                maps.insert(current_environment_entry.0, current_environment_entry.1);
-                current_environment_entry = (String::default(), Environment::default());
-            } else {
-                current_environment_entry.0 = String::from(&line[1..]);
-                relative_row = row;
-            }
+    // the map being read along with the line it was opened on
+    let mut current: Option<(String, Environment, usize)> = None;
 
-            // toggle reading state
-            reading = !reading;
-        } else {
-            for (col, sym) in line.chars().enumerate() {
-                match sym {
-                    // indestructable
-                    '1'..='5' => {
-                        let elevation = sym.to_digit(10).unwrap() as usize;
-                        current_environment_entry.1.tiles.insert(
-                            (row - relative_row, col),
-                            Tile::IndestructableWall(elevation),
-                        );
-                    }
-                    // destructable
-                    '6'..='9' => {
-                        let elevation = sym.to_digit(10).unwrap() as usize - 5;
-                        current_environment_entry.1.tiles.insert(
-                            (row - relative_row, col),
-                            Tile::DesructableWall((3, elevation)),
-                        );
-                    }
-                    // empty
-                    'x' => {
-                        current_environment_entry
-                            .1
-                            .tiles
-                            .insert((row - relative_row, col), Tile::Empty);
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |column, kind| MapParseError {
+            line: line_number,
+            column,
+            kind,
+        };
+
+        let Some((_, environment, _)) = &mut current else {
+            // outside of a map definition
+            if let Some(name) = line.strip_prefix(MAP_DELIMITER) {
+                if version >= 2 && name.trim().is_empty() {
+                    return Err(error(2, MapParseErrorKind::MissingMapName));
+                }
+                current = Some((String::from(name), Environment::default(), line_number));
+            } else if let Some(declared) = line.strip_prefix(VERSION_PREFIX) {
+                if !maps.is_empty() {
+                    return Err(error(1, MapParseErrorKind::UnexpectedContent));
+                }
+                version = match declared.trim().parse::<u32>() {
+                    Ok(v) if (1..=MAP_FORMAT_VERSION).contains(&v) => v,
+                    _ => {
+                        return Err(error(
+                            VERSION_PREFIX.len() + 2,
+                            MapParseErrorKind::UnsupportedVersion(String::from(declared.trim())),
+                        ))
                     }
-                    _ => (),
                 };
+            } else if version >= 2 && !line.trim().is_empty() && !line.starts_with(COMMENT_PREFIX) {
+                return Err(error(1, MapParseErrorKind::UnexpectedContent));
             }
+            continue;
+        };
+
+        if line.starts_with(MAP_DELIMITER) {
+            let (name, environment, _) = current.take().unwrap();
+            maps.push((name, environment));
+            continue;
+        }
+
+        if version >= 2 {
+            if let Some(entry) = line.strip_prefix(METADATA_PREFIX) {
+                if environment.height > 0 {
+                    return Err(error(1, MapParseErrorKind::MetadataAfterTiles));
+                }
+                parse_metadata(environment, entry).map_err(|kind| error(2, kind))?;
+                continue;
+            }
+        }
+
+        let row = environment.height;
+        environment.height += 1;
+
+        let row_width = line.chars().count();
+        if version >= 2 && row > 0 && row_width != environment.width {
+            return Err(error(
+                row_width.min(environment.width) + 1,
+                MapParseErrorKind::RaggedRow {
+                    expected: environment.width,
+                    found: row_width,
+                },
+            ));
+        }
+
+        for (col, sym) in line.chars().enumerate() {
+            if version >= 2 {
+                if !place_symbol(environment, (row, col), sym) {
+                    return Err(error(col + 1, MapParseErrorKind::UnknownSymbol(sym)));
+                }
+            } else if matches!(sym, '1'..='9' | 'x') {
+                // the first version only had walls, and every other symbol was ignored
+                place_symbol(environment, (row, col), sym);
+            }
+            environment.width = environment.width.max(col + 1);
+        }
+    }
+
+    if let Some((name, _, opened_on)) = current {
+        if version >= 2 {
+            return Err(MapParseError {
+                line: opened_on,
+                column: 1,
+                kind: MapParseErrorKind::UnterminatedMap(name),
+            });
         }
     }
 
     Ok(maps)
 }
+
+/// Record the meaning of a single map symbol at the (row, column) location.
+/// Returns false when the symbol is not part of the format.
+pub fn place_symbol(environment: &mut Environment, location: (usize, usize), sym: char) -> bool {
+    match sym {
+        // open ground
+        '.' | ' ' => (),
+        // indestructable
+        '1'..='5' => {
+            let elevation = sym.to_digit(10).unwrap() as usize;
+            environment
+                .tiles
+                .insert(location, Tile::IndestructableWall(elevation));
+        }
+        // destructable
+        '6'..='9' => {
+            let elevation = sym.to_digit(10).unwrap() as usize - 5;
+            environment
+                .tiles
+                .insert(location, Tile::DesructableWall((3, elevation)));
+        }
+        // empty
+        'x' => {
+            environment.tiles.insert(location, Tile::Empty);
+        }
+        // terrain
+        'o' => {
+            environment.tiles.insert(location, Tile::Hole);
+        }
+        'm' => {
+            environment.tiles.insert(location, Tile::Mud);
+        }
+        'w' => {
+            environment.tiles.insert(location, Tile::Water);
+        }
+        'i' => {
+            environment.tiles.insert(location, Tile::Ice);
+        }
+        // teleporter pairs
+        'T'..='W' => {
+            environment
+                .tiles
+                .insert(location, Tile::Teleporter(sym as usize - 'T' as usize));
+        }
+        // player spawns
+        'p' => environment.spawns.push(location),
+        // team zones
+        'A'..='D' => {
+            environment
+                .team_zones
+                .insert(location, sym as usize - 'A' as usize);
+        }
+        // bot wave spawners
+        '*' => environment.spawners.push(location),
+        _ => {
+            if let Some(archetype) = BotArchetype::from_symbol(sym) {
+                // enemy placements
+                environment.enemies.push(EnemyPlacement {
+                    location,
+                    archetype,
+                });
+            } else if let Some(kind) = PickupKind::from_symbol(sym) {
+                // pickups
+                environment.pickups.push(Pickup { location, kind });
+            } else {
+                return false;
+            }
+        }
+    };
+
+    true
+}
+
+/// Read a `key value` metadata entry into the environment
+fn parse_metadata(environment: &mut Environment, entry: &str) -> Result<(), MapParseErrorKind> {
+    let (key, value) = entry.split_once(' ').unwrap_or((entry, ""));
+    let value = value.trim();
+
+    let invalid = || MapParseErrorKind::InvalidMetadata {
+        key: String::from(key),
+        value: String::from(value),
+    };
+
+    match key {
+        "author" if !value.is_empty() => environment.metadata.author = Some(String::from(value)),
+        "players" => environment.metadata.players = Some(value.parse().map_err(|_| invalid())?),
+        "mode" => environment.metadata.mode = Some(MapMode::from_name(value).ok_or_else(invalid)?),
+        "author" => return Err(invalid()),
+        _ => return Err(MapParseErrorKind::UnknownMetadata(String::from(key))),
+    };
+
+    Ok(())
+}
+
+/// Malformed input found while reading a MapData file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapParseError {
+    /// Line of the file where the problem was found, starting from 1
+    pub line: usize,
+    /// Character within the line where the problem was found, starting from 1
+    pub column: usize,
+    pub kind: MapParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapParseErrorKind {
+    /// The file is not valid UTF-8 text
+    InvalidUtf8,
+    /// The `!version` line names a version that cannot be read
+    UnsupportedVersion(String),
+    /// A character in a map that is not part of the format
+    UnknownSymbol(char),
+    /// A row of tiles that is not as wide as the first row of its map
+    RaggedRow { expected: usize, found: usize },
+    /// A metadata entry with a key that is not part of the format
+    UnknownMetadata(String),
+    /// A metadata entry whose value could not be understood
+    InvalidMetadata { key: String, value: String },
+    /// Metadata must come before the first row of tiles
+    MetadataAfterTiles,
+    /// A map was opened without a name
+    MissingMapName,
+    /// The file ended before the map with this name was closed
+    UnterminatedMap(String),
+    /// Text outside of a map that is not a comment
+    UnexpectedContent,
+}
+
+impl MapParseError {
+    fn from_utf8(bytes: &[u8], error: Utf8Error) -> Self {
+        let valid = &bytes[..error.valid_up_to()];
+        let line_start = valid.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+
+        Self {
+            line: valid.iter().filter(|&&b| b == b'\n').count() + 1,
+            // columns count characters, and everything before the error is known to be valid
+            column: std::str::from_utf8(&valid[line_start..]).map_or(0, |s| s.chars().count()) + 1,
+            kind: MapParseErrorKind::InvalidUtf8,
+        }
+    }
+}
+
+impl fmt::Display for MapParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+
+        match &self.kind {
+            MapParseErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            MapParseErrorKind::UnsupportedVersion(v) => write!(f, "unsupported version '{}'", v),
+            MapParseErrorKind::UnknownSymbol(c) => write!(f, "unknown map symbol '{}'", c),
+            MapParseErrorKind::RaggedRow { expected, found } => {
+                write!(f, "row is {} tiles wide instead of {}", found, expected)
+            }
+            MapParseErrorKind::UnknownMetadata(k) => write!(f, "unknown metadata key '{}'", k),
+            MapParseErrorKind::InvalidMetadata { key, value } => {
+                write!(f, "invalid value '{}' for metadata '{}'", value, key)
+            }
+            MapParseErrorKind::MetadataAfterTiles => {
+                write!(f, "metadata must come before the map tiles")
+            }
+            MapParseErrorKind::MissingMapName => write!(f, "map is missing a name"),
+            MapParseErrorKind::UnterminatedMap(name) => {
+                write!(f, "map '{}' is never closed with '@'", name)
+            }
+            MapParseErrorKind::UnexpectedContent => {
+                write!(f, "unexpected text outside of a map definition")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapParseError {}
+
+/// Turns maps back into the text of a MapData file that [`parse_environments_file`] can read.
+/// Maps are written in order of their names so that the output is stable.
+pub fn write_environments_file<'a>(
+    maps: impl IntoIterator<Item = (&'a String, &'a Environment)>,
+) -> String {
+    let mut maps = maps.into_iter().collect::<Vec<_>>();
+    maps.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut file = format!("!version {}\n", MAP_FORMAT_VERSION);
+    for (name, environment) in maps {
+        file.push('\n');
+        file.push_str(&write_environment(name, environment));
+    }
+
+    file
+}
+
+/// Writes a single map definition, from its opening `@` to its closing `@`
+///
+/// A tile can only hold one symbol, so when several things share a tile the first of
+/// walls, spawns, team zones, spawners, enemies and pickups is written.
+/// Walls are written at full health and elevations are clamped into the range of their symbols.
+pub fn write_environment(name: &str, environment: &Environment) -> String {
+    let mut grid = HashMap::<(usize, usize), char>::new();
+
+    // written in reverse order of priority so that the more important symbols overwrite the rest
+    for pickup in &environment.pickups {
+        grid.insert(pickup.location, pickup.kind.symbol());
+    }
+    for enemy in &environment.enemies {
+        grid.insert(enemy.location, enemy.archetype.symbol());
+    }
+    for spawner in &environment.spawners {
+        grid.insert(*spawner, '*');
+    }
+    for (location, team) in &environment.team_zones {
+        grid.insert(*location, (b'A' + (*team).min(3) as u8) as char);
+    }
+    for spawn in &environment.spawns {
+        grid.insert(*spawn, 'p');
+    }
+    for (location, tile) in &environment.tiles {
+        grid.insert(*location, tile_symbol(tile));
+    }
+
+    let (height, width) = grid.keys().fold(
+        (environment.height, environment.width),
+        |(height, width), (row, col)| (height.max(row + 1), width.max(col + 1)),
+    );
+
+    let mut text = format!("@{}\n", name);
+
+    let metadata = &environment.metadata;
+    if let Some(author) = &metadata.author {
+        text.push_str(&format!(":author {}\n", author));
+    }
+    if let Some(players) = metadata.players {
+        text.push_str(&format!(":players {}\n", players));
+    }
+    if let Some(mode) = metadata.mode {
+        text.push_str(&format!(":mode {}\n", mode.name()));
+    }
+
+    for row in 0..height {
+        text.extend((0..width).map(|col| grid.get(&(row, col)).copied().unwrap_or('.')));
+        text.push('\n');
+    }
+
+    text.push_str("@\n");
+    text
+}
+
+fn tile_symbol(tile: &Tile) -> char {
+    match tile {
+        Tile::IndestructableWall(elevation) => {
+            char::from_digit((*elevation).clamp(1, 5) as u32, 10).unwrap()
+        }
+        Tile::DesructableWall((_, elevation)) => {
+            char::from_digit((*elevation).clamp(1, 4) as u32 + 5, 10).unwrap()
+        }
+        Tile::Empty => 'x',
+        Tile::Hole => 'o',
+        Tile::Mud => 'm',
+        Tile::Water => 'w',
+        Tile::Ice => 'i',
+        Tile::Teleporter(channel) => (b'T' + (*channel).min(3) as u8) as char,
+    }
+}
+
+/// Reads the ordered list of map names that make up a campaign
+///
+/// # Example
+///
+/// file contents:
+/// ```plaintext
+/// # levels are played from top to bottom
+/// campaign-1
+/// campaign-2
+/// ```
+pub fn parse_campaign_file(campaign_bytes: &[u8]) -> Result<Vec<String>, Utf8Error> {
+    const COMMENT: char = '#';
+
+    Ok(std::str::from_utf8(campaign_bytes)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(COMMENT))
+        .map(String::from)
+        .collect())
+}
@@ -0,0 +1,179 @@
+//! Computer controlled Tanks which drive themselves using the same actions as a Player
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::Vector2;
+
+use super::{gamestate::GameState, health::ProjectileKind, player::Player};
+
+/// The flavours of enemy that can be placed into a map
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum BotArchetype {
+    /// Sits still and takes the occasional shot
+    Rookie,
+    /// Holds its distance while firing steadily
+    Gunner,
+    /// Chases down the closest enemy and fires rapidly
+    Hunter,
+}
+
+impl BotArchetype {
+    /// Map symbol used to place this archetype in a MapData file
+    pub fn symbol(&self) -> char {
+        match self {
+            BotArchetype::Rookie => 'r',
+            BotArchetype::Gunner => 'g',
+            BotArchetype::Hunter => 'h',
+        }
+    }
+
+    pub fn from_symbol(sym: char) -> Option<Self> {
+        match sym {
+            'r' => Some(BotArchetype::Rookie),
+            'g' => Some(BotArchetype::Gunner),
+            'h' => Some(BotArchetype::Hunter),
+            _ => None,
+        }
+    }
+
+    /// Number of GameState ticks between shots
+    pub fn fire_interval(&self) -> u32 {
+        match self {
+            BotArchetype::Rookie => 150,
+            BotArchetype::Gunner => 80,
+            BotArchetype::Hunter => 45,
+        }
+    }
+
+    /// How heavy the Tank of the bot is compared to a standard Tank
+    pub fn mass(&self) -> f64 {
+        match self {
+            BotArchetype::Rookie => 1.0,
+            BotArchetype::Gunner => 1.5,
+            BotArchetype::Hunter => 0.8,
+        }
+    }
+
+    /// The kind of projectile the bot fires
+    pub fn projectile(&self) -> ProjectileKind {
+        match self {
+            BotArchetype::Rookie => ProjectileKind::Shell,
+            BotArchetype::Gunner => ProjectileKind::Heavy,
+            BotArchetype::Hunter => ProjectileKind::Light,
+        }
+    }
+
+    /// Distance the bot tries to keep from its target, or None if it never moves
+    pub fn preferred_range(&self) -> Option<f64> {
+        match self {
+            BotArchetype::Rookie => None,
+            BotArchetype::Gunner => Some(7.0),
+            BotArchetype::Hunter => Some(2.0),
+        }
+    }
+}
+
+/// Control state for a single computer controlled Tank
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bot {
+    pub archetype: BotArchetype,
+    /// Ticks remaining until the bot is allowed to shoot again
+    pub cooldown: u32,
+}
+
+impl Bot {
+    pub fn new(archetype: BotArchetype) -> Self {
+        Self {
+            archetype,
+            cooldown: archetype.fire_interval(),
+        }
+    }
+}
+
+/// The team that every bot joins unless told otherwise
+pub const BOT_TEAM: usize = 1;
+
+impl GameState {
+    /// Add a bot controlled Tank to the game at the given position
+    pub fn spawn_bot(&mut self, id: String, archetype: BotArchetype, position: Vector2) {
+        let mut player = Player::new(id.clone());
+        player.team = BOT_TEAM;
+        player.position = position;
+        player.mass = archetype.mass();
+        player.projectile = archetype.projectile();
+
+        self.bots.insert(id.clone(), Bot::new(archetype));
+        player.set_durability(self.durability(&id));
+        self.players.insert(id, player);
+    }
+
+    /// Remove every bot and its Tank from the game
+    pub fn clear_bots(&mut self) {
+        for id in self.bots.keys() {
+            self.players.remove(id);
+        }
+        self.bots.clear();
+    }
+
+    /// Number of bots whose Tanks are still alive
+    pub fn bots_remaining(&self) -> usize {
+        self.bots
+            .keys()
+            .filter(|id| self.players.get(*id).is_some_and(|p| p.alive))
+            .count()
+    }
+
+    /// Decide on the movement, aim and shots of every bot for this tick
+    pub(crate) fn update_bots(&mut self) {
+        let mut ids = self.bots.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+
+        for id in ids {
+            let Some(me) = self.players.get(&id).filter(|p| p.alive) else {
+                continue;
+            };
+
+            let target = self
+                .players
+                .values()
+                .filter(|p| p.alive && self.are_hostile(me, p))
+                .map(|p| p.position)
+                .min_by(|a, b| {
+                    let da = a.plus(&me.position.scale(-1.0)).magnitude();
+                    let db = b.plus(&me.position.scale(-1.0)).magnitude();
+                    da.total_cmp(&db)
+                });
+
+            let Some(target) = target else {
+                self.set_player_movement(&id, &Vector2::zero());
+                continue;
+            };
+
+            let offset = target.plus(&me.position.scale(-1.0));
+            let bot = self.bots.get_mut(&id).expect("bot ids were just collected");
+
+            let movement = match bot.archetype.preferred_range() {
+                Some(range) if offset.magnitude() > range + 0.5 => offset,
+                Some(range) if offset.magnitude() < range - 0.5 => offset.scale(-1.0),
+                _ => Vector2::zero(),
+            };
+
+            let shoot = match bot.cooldown {
+                0 => {
+                    bot.cooldown = bot.archetype.fire_interval();
+                    true
+                }
+                _ => {
+                    bot.cooldown -= 1;
+                    false
+                }
+            };
+
+            self.set_player_angle(&id, offset.y.atan2(offset.x));
+            self.set_player_movement(&id, &movement);
+            if shoot {
+                self.player_shoot(&id);
+            }
+        }
+    }
+}
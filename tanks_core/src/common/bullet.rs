@@ -2,15 +2,22 @@
 
 use std::f64::consts::PI;
 
+use serde::{Deserialize, Serialize};
+
 use crate::utils::Vector2;
 
-use super::constants::BULLET_SPEED;
+use super::{
+    constants::{BULLET_RICOCHETS, BULLET_SPEED},
+    health::ProjectileKind,
+};
 
 /// Projectile shot from a Tank that will bounce off walls and destroy other Tanks (Players)
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Bullet {
     /// The ID of the player who created the bullet
     pub player_id: String,
+    /// Decides how much damage the Bullet deals when the health model is enabled
+    pub kind: ProjectileKind,
     /// Bullet Position
     pub position: Vector2,
     /// Speed of the Bullet
@@ -22,6 +29,11 @@ pub struct Bullet {
 }
 
 impl Bullet {
+    /// Number of times the Bullet has bounced off of walls since it was fired
+    pub fn bounces(&self) -> u8 {
+        BULLET_RICOCHETS.saturating_sub(self.ricochets)
+    }
+
     pub fn add_angle(&mut self, turn: f64) {
         self.angle = (self.angle + turn) % (2.0 * PI);
         self.velocity.x = BULLET_SPEED * self.angle.cos();
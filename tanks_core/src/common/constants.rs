@@ -11,8 +11,15 @@ pub const BULLET_RADIUS: f64 = 0.12;
 pub const BULLET_COUNT: u8 = 5;
 /// Speed of the Bullet relative to the map size
 pub const BULLET_SPEED: f64 = 0.12;
+/// Number of times a Bullet bounces off of walls before it explodes
+pub const BULLET_RICOCHETS: u8 = 1;
 
-/// Speed of the Player relative to the map size
+/// Half of the width of the square hull of a Tank relative to the map size
 pub const PLAYER_RADIUS: f64 = 0.4;
 /// Speed of the Player relative to the map size
 pub const PLAYER_SPEED: f64 = 0.08;
+/// Mass of a standard Tank, which decides how far it is pushed by other Tanks
+pub const PLAYER_MASS: f64 = 1.0;
+/// Closing speed at which a Tank destroys a hostile Tank that it drives into, when ramming is enabled.
+/// This is faster than a single Tank can drive, so only head-on collisions destroy a Tank.
+pub const RAM_SPEED: f64 = PLAYER_SPEED * 1.5;
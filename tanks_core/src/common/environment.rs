@@ -1,17 +1,220 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet, VecDeque};
 
-#[derive(Debug, Default)]
+use serde::{Deserialize, Serialize};
+
+use crate::utils::Vector2;
+
+use super::bot::BotArchetype;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct Environment {
-    pub tiles: HashMap<(usize, usize), Tile>,
+    #[serde(with = "crate::utils::map_as_pairs")]
+    pub tiles: BTreeMap<(usize, usize), Tile>,
+    /// Number of columns in the map definition
+    pub width: usize,
+    /// Number of rows in the map definition
+    pub height: usize,
+    /// Bots that are placed into the map when it is loaded
+    pub enemies: Vec<EnemyPlacement>,
+    /// (row, column) of the tiles where waves of bots enter the map
+    pub spawners: Vec<(usize, usize)>,
+    /// (row, column) of the tiles where player Tanks can start
+    pub spawns: Vec<(usize, usize)>,
+    /// Tiles that belong to a team, mapped to the index of that team
+    #[serde(with = "crate::utils::map_as_pairs")]
+    pub team_zones: BTreeMap<(usize, usize), usize>,
+    pub pickups: Vec<Pickup>,
+    pub metadata: MapMetadata,
+}
+
+impl Environment {
+    /// Every tile a Tank could drive to from the start without passing through a wall
+    pub fn reachable_from(&self, start: (usize, usize)) -> HashSet<(usize, usize)> {
+        let mut reachable = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(location) = queue.pop_front() {
+            for next in neighbours(location, self.width, self.height) {
+                if !self.tiles.get(&next).is_some_and(Tile::blocks_tanks) && reachable.insert(next)
+                {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// The (row, column) of the tile containing a position
+    pub fn location_of(position: &Vector2) -> Option<(usize, usize)> {
+        (position.x >= 0.0 && position.y >= 0.0)
+            .then(|| (position.y.floor() as usize, position.x.floor() as usize))
+    }
+
+    /// The tile underneath a position, if it is not open ground
+    pub fn tile_at(&self, position: &Vector2) -> Option<&Tile> {
+        Self::location_of(position).and_then(|location| self.tiles.get(&location))
+    }
+
+    /// The (row, column) of the teleporter that a Tank on the given tile would be sent to
+    pub fn teleporter_partner(&self, location: (usize, usize)) -> Option<(usize, usize)> {
+        let Some(Tile::Teleporter(channel)) = self.tiles.get(&location) else {
+            return None;
+        };
+
+        // the lowest location keeps the choice stable if a map has more than a pair
+        self.tiles
+            .iter()
+            .filter(|(other, tile)| **other != location && **tile == Tile::Teleporter(*channel))
+            .map(|(other, _)| *other)
+            .min()
+    }
+}
+
+/// The tiles directly above, below, left and right of a (row, column) that are within the bounds
+pub fn neighbours(
+    (row, col): (usize, usize),
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    [
+        (row.wrapping_sub(1), col),
+        (row + 1, col),
+        (row, col.wrapping_sub(1)),
+        (row, col + 1),
+    ]
+    .into_iter()
+    .filter(move |&(r, c)| r < height && c < width)
 }
 
 /// Ground or Wall objects that get displayed
 /// and have collisions for the Tanks or the Projectiles
-#[derive(Debug, Eq, PartialEq, PartialOrd)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, PartialOrd, Hash)]
 pub enum Tile {
     IndestructableWall(usize),
     // Left:    health of the wall
     // Right:   height of the wall
     DesructableWall((usize, usize)),
     Empty,
+    /// A pit that Tanks cannot drive into, but bullets fly over
+    Hole,
+    /// Ground that slows Tanks down
+    Mud,
+    /// Deeper ground that slows Tanks down even more than mud
+    Water,
+    /// Slippery ground where Tanks keep their momentum
+    Ice,
+    /// Sends a Tank to the other teleporter on the same channel
+    Teleporter(usize),
+}
+
+impl Tile {
+    /// Whether a Tank is unable to drive through the tile
+    pub fn blocks_tanks(&self) -> bool {
+        matches!(
+            self,
+            Tile::IndestructableWall(_) | Tile::DesructableWall((1.., _)) | Tile::Hole
+        )
+    }
+
+    /// Whether a bullet bounces off of the tile
+    pub fn blocks_bullets(&self) -> bool {
+        matches!(
+            self,
+            Tile::IndestructableWall(_) | Tile::DesructableWall((1.., _))
+        )
+    }
+
+    /// Multiplier on the speed of a Tank driving over the tile
+    pub fn speed_factor(&self) -> f64 {
+        match self {
+            Tile::Mud => 0.6,
+            Tile::Water => 0.35,
+            _ => 1.0,
+        }
+    }
+}
+
+/// A bot enemy and the (row, column) of the tile it starts on
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct EnemyPlacement {
+    pub location: (usize, usize),
+    pub archetype: BotArchetype,
+}
+
+/// An item placed on a (row, column) tile of the map
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Pickup {
+    pub location: (usize, usize),
+    pub kind: PickupKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PickupKind {
+    /// Refills the bullets of the Tank
+    Ammo,
+    /// Temporarily increases the movement speed of the Tank
+    Speed,
+    /// Absorbs the next hit on the Tank
+    Shield,
+}
+
+impl PickupKind {
+    /// Map symbol used to place this pickup in a MapData file
+    pub fn symbol(&self) -> char {
+        match self {
+            PickupKind::Ammo => '+',
+            PickupKind::Speed => '>',
+            PickupKind::Shield => '$',
+        }
+    }
+
+    pub fn from_symbol(sym: char) -> Option<Self> {
+        match sym {
+            '+' => Some(PickupKind::Ammo),
+            '>' => Some(PickupKind::Speed),
+            '$' => Some(PickupKind::Shield),
+            _ => None,
+        }
+    }
+}
+
+/// Descriptive information about a map which does not affect how it is played
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Eq, PartialEq)]
+pub struct MapMetadata {
+    pub author: Option<String>,
+    /// Number of players the map was designed for
+    pub players: Option<usize>,
+    /// The game mode the map was designed for
+    pub mode: Option<MapMode>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum MapMode {
+    Versus,
+    Campaign,
+    Survival,
+    BattleRoyale,
+}
+
+impl MapMode {
+    /// Name used for this mode in a MapData file
+    pub fn name(&self) -> &'static str {
+        match self {
+            MapMode::Versus => "versus",
+            MapMode::Campaign => "campaign",
+            MapMode::Survival => "survival",
+            MapMode::BattleRoyale => "royale",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "versus" => Some(MapMode::Versus),
+            "campaign" => Some(MapMode::Campaign),
+            "survival" => Some(MapMode::Survival),
+            "royale" => Some(MapMode::BattleRoyale),
+            _ => None,
+        }
+    }
 }
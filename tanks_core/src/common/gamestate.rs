@@ -1,19 +1,61 @@
-use std::collections::{BTreeSet, HashMap};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use serde::{Deserialize, Serialize};
 
-use crate::utils::{circle_circle_collision, circle_rect_collision, Vector2};
+use crate::utils::{
+    box_box_collision, circle_box_collision, circle_circle_collision, circle_rect_collision,
+    OrientedBox, Rng, StableHasher, Vector2,
+};
 
 use super::{
+    bot::Bot,
     bullet::Bullet,
-    constants::{BULLET_RADIUS, BULLET_SPEED, MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH, PLAYER_RADIUS},
-    environment::{Environment, Tile},
+    constants::{
+        BULLET_RADIUS, BULLET_RICOCHETS, BULLET_SPEED, MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH, RAM_SPEED,
+    },
+    environment::Environment,
+    health::{DamageEvent, HealthConfig},
     player::{Player, TankState},
+    zone::SafeZone,
 };
 
-#[derive(Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct GameState {
-    pub players: HashMap<String, Player>,
+    /// Number of ticks the simulation has run for
+    pub ticks: u64,
+    /// Source of every random choice made during the simulation,
+    /// so that it plays out the same way from the same seed and inputs
+    pub rng: Rng,
+    /// Kept in order of their ids so that the Tanks are always updated in the same order
+    pub players: BTreeMap<String, Player>,
+    /// Control state for the players which are driven by the computer
+    pub bots: BTreeMap<String, Bot>,
     pub bullets: Vec<Bullet>,
     pub environment: Environment,
+    /// Area outside of which Tanks are eliminated, when playing a battle royale
+    pub zone: Option<SafeZone>,
+    /// Whether Tanks on the same team are friendly, otherwise it is every Tank for itself
+    pub teams: bool,
+    /// Whether driving into a hostile Tank fast enough destroys it
+    pub ramming: bool,
+    /// Hit points and armor of the Tanks, otherwise any hit destroys a Tank
+    pub health: Option<HealthConfig>,
+    /// Hits that Tanks took from projectiles during the last tick
+    pub damage_events: Vec<DamageEvent>,
+}
+
+impl GameState {
+    /// An empty game whose random choices are made from the seed
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            ..Default::default()
+        }
+    }
 }
 
 /// Implementations for every sensical action that can be taken during the game
@@ -32,13 +74,13 @@ impl GameState {
     }
 
     /// Spawn a bullet from the player who invoked the shoot action
-    /// Uses the angle and position of the indicated player
+    /// Uses the angle and position of the indicated player, and does nothing for unknown players
     pub fn player_shoot(&mut self, player_id: &str) {
         let Some(player) = self.players.get_mut(player_id) else {
-            todo!() // trying to move a player thats not in the game?
+            return;
         };
 
-        if matches!(player.state, TankState::Idle) && player.bullets_remaining > 0 {
+        if player.alive && matches!(player.state, TankState::Idle) && player.bullets_remaining > 0 {
             // decrement available bullets
             player.bullets_remaining -= 1;
 
@@ -47,11 +89,17 @@ impl GameState {
                 BULLET_SPEED * player.gun_angle.sin(),
             );
 
+            // start the bullet at the end of the gun so that it does not hit the shooter
+            let direction = velocity.normalize();
+            let muzzle = direction
+                .scale(player.hull().projected_radius(&direction) + BULLET_RADIUS + BULLET_SPEED);
+
             self.bullets.push(Bullet {
+                kind: player.projectile,
                 velocity,
-                ricochets: 1,
+                ricochets: BULLET_RICOCHETS,
                 angle: player.gun_angle,
-                position: player.position,
+                position: player.position.plus(&muzzle),
                 player_id: player.id.clone(),
             });
 
@@ -64,42 +112,118 @@ impl GameState {
 impl GameState {
     /// Get list of references to player IDs
     pub fn get_client_ids(&self) -> Vec<&String> {
-        self.players.iter().map(|(id, _)| id).collect()
+        self.players.keys().collect()
+    }
+
+    /// Whether the Tanks are enemies of one another
+    pub fn are_hostile(&self, a: &Player, b: &Player) -> bool {
+        a.id != b.id && !(self.teams && a.team == b.team)
+    }
+
+    /// Fingerprint of everything that changes while the game is played,
+    /// which is only equal for GameStates that are bit for bit identical
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = StableHasher::default();
+        let h = &mut hasher;
+
+        self.ticks.hash(h);
+        self.rng.hash(h);
+        self.teams.hash(h);
+        self.ramming.hash(h);
+
+        for player in self.players.values() {
+            player.id.hash(h);
+            player.team.hash(h);
+            player.alive.hash(h);
+            match player.state {
+                TankState::Shooting(ticks) => Some(ticks).hash(h),
+                TankState::Idle => None::<u32>.hash(h),
+            }
+            hash_vector(h, &player.position);
+            hash_vector(h, &player.movement);
+            hash_vector(h, &player.velocity);
+            for value in [
+                player.gun_angle,
+                player.movement_dir,
+                player.hull_angle,
+                player.mass,
+            ] {
+                value.to_bits().hash(h);
+            }
+            player.bullets_remaining.hash(h);
+            player.projectile.hash(h);
+            player.hit_points.hash(h);
+            player.max_hit_points.hash(h);
+            player.armor.hash(h);
+        }
+
+        for (id, bot) in &self.bots {
+            id.hash(h);
+            bot.archetype.hash(h);
+            bot.cooldown.hash(h);
+        }
+
+        for bullet in &self.bullets {
+            bullet.player_id.hash(h);
+            bullet.kind.hash(h);
+            hash_vector(h, &bullet.position);
+            hash_vector(h, &bullet.velocity);
+            bullet.angle.to_bits().hash(h);
+            bullet.ricochets.hash(h);
+        }
+
+        self.environment.tiles.hash(h);
+
+        if let Some(zone) = &self.zone {
+            hash_vector(h, &zone.center);
+            zone.radius.to_bits().hash(h);
+            zone.next_radius.to_bits().hash(h);
+            zone.countdown.hash(h);
+        }
+
+        hasher.finish()
     }
 }
 
 impl GameState {
     pub fn tick(&mut self) {
+        self.ticks += 1;
+        self.damage_events.clear();
+
+        // let the computer controlled tanks choose their actions
+        self.update_bots();
+
+        // remember where the Tanks were so that only arriving on a teleporter sends them away
+        let previous_locations = self
+            .players
+            .values()
+            .map(|player| {
+                (
+                    player.id.clone(),
+                    Environment::location_of(&player.position),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
         // update physics steps for entities
         self.bullets.iter_mut().for_each(Bullet::physics_update);
-        self.players
-            .iter_mut()
-            .map(|(_, player)| player)
-            .for_each(Player::physics_update);
+        for player in self.players.values_mut().filter(|player| player.alive) {
+            player.physics_update(self.environment.tile_at(&player.position));
+        }
 
         // Process bullet collisions which results in the bullet exploding or dissappearing.
         // These interactions should also result in a reallocation of bullet shots to the
         // player who created the bullet.
 
         // collisions of bullets with players
-        for player in self
-            .players
-            .iter_mut()
-            // only compute collisions with bullets when the player is alive
-            .filter_map(|(_, p)| if p.alive { Some(p) } else { None })
+        for freed_bullet_player_id in self
+            .bullet_collisions_with_players()
+            .into_iter()
+            .rev()
+            .map(|i| self.bullets.remove(i).player_id)
         {
-            for bullet in self.bullets.iter() {
-                if circle_circle_collision(
-                    &player.position,
-                    PLAYER_RADIUS,
-                    &bullet.position,
-                    BULLET_RADIUS,
-                )
-                .is_ok()
-                {
-                    player.alive = false;
-                    break; // skip to another player
-                }
+            if let Some(player) = self.players.get_mut(&freed_bullet_player_id) {
+                player.bullets_remaining += 1;
             }
         }
 
@@ -127,22 +251,20 @@ impl GameState {
             }
         }
 
+        // collisions of players with other players
+        self.collisions_between_players();
+
         // collisions of players with tiles
-        for player in self.players.iter_mut().map(|(_, p)| p) {
+        for player in self.players.values_mut() {
             for (loc, tile) in &self.environment.tiles {
-                if let Tile::DesructableWall((0, _)) = tile {
-                    if let Err(Vector2 { x, y }) = circle_rect_collision(
-                        &player.position,
-                        PLAYER_RADIUS,
-                        &Vector2::new(loc.0 as _, loc.1 as _),
-                        1.0,
-                        1.0,
-                    ) {
-                        // push the tanks out of the collision box
-                        player.position = player
-                            .position
-                            .plus(&Vector2::new(PLAYER_RADIUS - x, PLAYER_RADIUS - y));
-                    }
+                if !tile.blocks_tanks() {
+                    continue;
+                }
+
+                let tile_box = OrientedBox::axis_aligned(&tile_corner(*loc), 1.0, 1.0);
+                if let Err(push) = box_box_collision(&tile_box, &player.hull()) {
+                    // push the tanks out of the collision box the shortest way
+                    player.position = player.position.plus(&push);
                 }
             }
         }
@@ -161,16 +283,153 @@ impl GameState {
 
         // collisions of players with bounds
         for player in self.players.iter_mut().map(|(_, p)| p) {
-            if player.position.x + PLAYER_RADIUS >= MAP_BLOCK_WIDTH as _ {
-                player.position.x = MAP_BLOCK_WIDTH as f64 - PLAYER_RADIUS;
-            } else if player.position.x - PLAYER_RADIUS <= 0.0 {
-                player.position.x = PLAYER_RADIUS;
+            // a turned hull reaches further out than its width
+            let bounds = player.hull().bounds();
+
+            if player.position.x + bounds.x >= MAP_BLOCK_WIDTH as _ {
+                player.position.x = MAP_BLOCK_WIDTH as f64 - bounds.x;
+            } else if player.position.x - bounds.x <= 0.0 {
+                player.position.x = bounds.x;
             }
 
-            if player.position.y + PLAYER_RADIUS >= MAP_BLOCK_HEIGHT as _ {
-                player.position.y = MAP_BLOCK_HEIGHT as f64 - PLAYER_RADIUS;
-            } else if player.position.y - PLAYER_RADIUS <= 0.0 {
-                player.position.y = PLAYER_RADIUS;
+            if player.position.y + bounds.y >= MAP_BLOCK_HEIGHT as _ {
+                player.position.y = MAP_BLOCK_HEIGHT as f64 - bounds.y;
+            } else if player.position.y - bounds.y <= 0.0 {
+                player.position.y = bounds.y;
+            }
+        }
+
+        // send the tanks that drove onto a teleporter over to its partner
+        for player in self.players.values_mut().filter(|player| player.alive) {
+            let location = Environment::location_of(&player.position);
+            if location == previous_locations.get(&player.id).copied().flatten() {
+                continue;
+            }
+
+            if let Some((row, col)) = location.and_then(|l| self.environment.teleporter_partner(l))
+            {
+                player.position = Vector2::new(col as f64 + 0.5, row as f64 + 0.5);
+            }
+        }
+
+        // eliminate the tanks that are caught outside of the safe zone
+        self.apply_zone();
+    }
+
+    /// Damages the Tanks that are hit by a hostile bullet, destroying those that run out of hit points
+    /// Returns the indicies of bullets which should need to be removed
+    fn bullet_collisions_with_players(&mut self) -> BTreeSet<usize> {
+        let mut set = BTreeSet::new();
+
+        let teams = self
+            .players
+            .iter()
+            .map(|(id, player)| (id.clone(), player.team))
+            .collect::<HashMap<_, _>>();
+
+        for player in self
+            .players
+            .values_mut()
+            // only compute collisions with bullets when the player is alive
+            .filter(|p| p.alive)
+        {
+            for (i, bullet) in self.bullets.iter().enumerate() {
+                // teammates cannot hurt each other, but your own bullets can still hurt you
+                let friendly = self.teams
+                    && bullet.player_id != player.id
+                    && teams.get(&bullet.player_id) == Some(&player.team);
+
+                if !friendly
+                    && circle_box_collision(&bullet.position, BULLET_RADIUS, &player.hull())
+                        .is_err()
+                {
+                    let damage = match &self.health {
+                        Some(health) => health.damage(bullet.kind, player.armor),
+                        // without hit points every hit is fatal
+                        None => player.hit_points,
+                    };
+
+                    player.hit_points = player.hit_points.saturating_sub(damage);
+                    player.alive = player.hit_points > 0;
+
+                    self.damage_events.push(DamageEvent {
+                        target: player.id.clone(),
+                        source: bullet.player_id.clone(),
+                        kind: bullet.kind,
+                        damage,
+                        hit_points: player.hit_points,
+                        ricochets: bullet.bounces(),
+                    });
+
+                    set.insert(i);
+                    break; // skip to another player
+                }
+            }
+        }
+
+        set
+    }
+
+    /// Pushes apart the Tanks that overlap, with lighter Tanks being pushed further
+    ///
+    /// Pairs are resolved in order of their ids so that the outcome does not depend on
+    /// the iteration order of the players.
+    fn collisions_between_players(&mut self) {
+        let mut ids = self
+            .players
+            .values()
+            .filter(|p| p.alive)
+            .map(|p| p.id.clone())
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        for (i, a_id) in ids.iter().enumerate() {
+            for b_id in &ids[i + 1..] {
+                let (a, b) = (&self.players[a_id], &self.players[b_id]);
+                if !a.alive || !b.alive {
+                    continue;
+                }
+
+                let Err(separation) = box_box_collision(&a.hull(), &b.hull()) else {
+                    continue;
+                };
+
+                // direction from a to b along which the hulls overlap the least
+                let overlap = separation.magnitude();
+                let normal = separation.normalize();
+
+                if self.ramming && self.are_hostile(a, b) {
+                    // speed of each tank towards the other
+                    let a_speed = a.velocity.dot(&normal);
+                    let b_speed = -b.velocity.dot(&normal);
+
+                    if a_speed + b_speed >= RAM_SPEED {
+                        // the slower Tank is destroyed, or both when neither is faster,
+                        // so that the outcome does not depend on the names of the players
+                        let rammed = match a_speed.partial_cmp(&b_speed) {
+                            Some(Ordering::Greater) => vec![b_id],
+                            Some(Ordering::Less) => vec![a_id],
+                            _ => vec![a_id, b_id],
+                        };
+                        for id in rammed {
+                            if let Some(rammed) = self.players.get_mut(id) {
+                                rammed.alive = false;
+                            }
+                        }
+                        continue;
+                    }
+                }
+
+                let total_mass = a.mass + b.mass;
+                let a_push = normal.scale(-overlap * b.mass / total_mass);
+                let b_push = normal.scale(overlap * a.mass / total_mass);
+
+                if let Some(a) = self.players.get_mut(a_id) {
+                    a.position = a.position.plus(&a_push);
+                }
+                if let Some(b) = self.players.get_mut(b_id) {
+                    b.position = b.position.plus(&b_push);
+                }
             }
         }
     }
@@ -206,30 +465,36 @@ impl GameState {
 
         for (i, bullet) in &mut self.bullets.iter_mut().enumerate() {
             for (loc, tile) in &self.environment.tiles {
-                if let Tile::DesructableWall((0, _)) = tile {
-                    if let Err(collision) = circle_rect_collision(
-                        &bullet.position,
-                        PLAYER_RADIUS,
-                        &Vector2::new(loc.0 as _, loc.1 as _),
-                        1.0,
-                        1.0,
-                    ) {
-                        if bullet.ricochets > 0 {
-                            bullet.ricochets -= 1;
-                            // this is faster due to the assumption that all walls are either horizontal or vertically aligned
-                            bullet.velocity.x *= match collision.x > 0.0 {
-                                true => collision.x.signum(),
-                                false => 1.0,
-                            };
-                            bullet.velocity.y *= match collision.y > 0.0 {
-                                true => collision.y.signum(),
-                                false => 1.0,
-                            }
-                        } else {
-                            set.insert(i);
+                if !tile.blocks_bullets() {
+                    continue;
+                }
+
+                if let Err(offset) = circle_rect_collision(
+                    &bullet.position,
+                    BULLET_RADIUS,
+                    &tile_corner(*loc),
+                    1.0,
+                    1.0,
+                ) {
+                    // a bullet that is already moving away from the wall has bounced off of it
+                    if bullet.velocity.x * offset.x + bullet.velocity.y * offset.y <= 0.0 {
+                        continue;
+                    }
+
+                    if bullet.ricochets > 0 {
+                        bullet.ricochets -= 1;
+                        // this is faster due to the assumption that all walls are either horizontal or vertically aligned
+                        match offset.x.abs() > offset.y.abs() {
+                            true => bullet.velocity.x = -bullet.velocity.x,
+                            false => bullet.velocity.y = -bullet.velocity.y,
                         }
-                        // reverse the velocity of the bullet
+                        bullet.angle = bullet.velocity.y.atan2(bullet.velocity.x);
+                    } else {
+                        set.insert(i);
                     }
+
+                    // only bounce off of one wall per tick
+                    break;
                 }
             }
         }
@@ -264,3 +529,14 @@ impl GameState {
         set
     }
 }
+
+/// Hash the exact bits of a vector, since floats do not implement [`Hash`]
+fn hash_vector(hasher: &mut impl Hasher, vector: &Vector2) {
+    vector.x.to_bits().hash(hasher);
+    vector.y.to_bits().hash(hasher);
+}
+
+/// Top left corner of the tile at a (row, column) location
+fn tile_corner((row, col): (usize, usize)) -> Vector2 {
+    Vector2::new(col as f64, row as f64)
+}
@@ -0,0 +1,141 @@
+//! Optional hit points and armor, which let Tanks survive more than a single hit
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{bot::BotArchetype, gamestate::GameState};
+
+/// The kinds of round a Tank can fire, which differ in how much damage they deal
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum ProjectileKind {
+    /// Standard round fired by players
+    #[default]
+    Shell,
+    /// Weak round for rapid fire
+    Light,
+    /// Hard hitting round that gets through armor
+    Heavy,
+}
+
+impl ProjectileKind {
+    /// Damage dealt when the config does not say otherwise
+    pub fn base_damage(&self) -> u32 {
+        match self {
+            ProjectileKind::Shell => 2,
+            ProjectileKind::Light => 1,
+            ProjectileKind::Heavy => 3,
+        }
+    }
+}
+
+/// How much punishment a Tank can take
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Durability {
+    /// Hit points of the Tank when it enters the game
+    pub hit_points: u32,
+    /// Subtracted from the damage of every hit, though a hit always deals at least 1 damage
+    pub armor: u32,
+}
+
+impl Durability {
+    /// A Tank that is destroyed by any hit
+    pub const FRAGILE: Self = Self {
+        hit_points: 1,
+        armor: 0,
+    };
+}
+
+/// Settings for a session where Tanks have hit points,
+/// usually read from a JSON file where every field can be left out
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Durability of the Tanks of players
+    pub tanks: Durability,
+    /// Durability of bot Tanks by archetype, falling back to that of players
+    pub bots: HashMap<BotArchetype, Durability>,
+    /// Damage dealt by each kind of projectile, falling back to its base damage
+    pub damage: HashMap<ProjectileKind, u32>,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            tanks: Durability {
+                hit_points: 4,
+                armor: 0,
+            },
+            bots: HashMap::from([
+                (
+                    BotArchetype::Rookie,
+                    Durability {
+                        hit_points: 2,
+                        armor: 0,
+                    },
+                ),
+                (
+                    BotArchetype::Gunner,
+                    Durability {
+                        hit_points: 4,
+                        armor: 1,
+                    },
+                ),
+                (
+                    BotArchetype::Hunter,
+                    Durability {
+                        hit_points: 3,
+                        armor: 0,
+                    },
+                ),
+            ]),
+            damage: HashMap::new(),
+        }
+    }
+}
+
+impl HealthConfig {
+    /// Durability of a bot of the archetype, or of a player Tank when there is none
+    pub fn durability(&self, archetype: Option<BotArchetype>) -> Durability {
+        archetype
+            .and_then(|archetype| self.bots.get(&archetype))
+            .copied()
+            .unwrap_or(self.tanks)
+    }
+
+    /// Damage a projectile deals to a Tank with the given armor
+    pub fn damage(&self, kind: ProjectileKind, armor: u32) -> u32 {
+        let damage = self
+            .damage
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| kind.base_damage());
+
+        damage.saturating_sub(armor).max(1)
+    }
+}
+
+/// A Tank was hit by a projectile
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DamageEvent {
+    /// The Tank that was hit
+    pub target: String,
+    /// The Tank that fired the projectile
+    pub source: String,
+    pub kind: ProjectileKind,
+    pub damage: u32,
+    /// Hit points the target has left, where 0 means it was destroyed
+    pub hit_points: u32,
+    /// Number of times the projectile bounced off of walls before the hit
+    pub ricochets: u8,
+}
+
+impl GameState {
+    /// Durability of the Tank, which only varies when the health model is enabled
+    pub(crate) fn durability(&self, player_id: &str) -> Durability {
+        match &self.health {
+            Some(health) => health.durability(self.bots.get(player_id).map(|bot| bot.archetype)),
+            None => Durability::FRAGILE,
+        }
+    }
+}
@@ -1,5 +1,9 @@
+pub mod bot;
 pub mod bullet;
 pub mod constants;
 pub mod environment;
 pub mod gamestate;
+pub mod health;
 pub mod player;
+pub mod spawn;
+pub mod zone;
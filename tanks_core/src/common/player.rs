@@ -1,8 +1,14 @@
-use crate::utils::Vector2;
+use serde::{Deserialize, Serialize};
 
-use super::constants::{BULLET_COUNT, PLAYER_SPEED};
+use crate::utils::{OrientedBox, Vector2};
 
-#[derive(Debug)]
+use super::{
+    constants::{BULLET_COUNT, PLAYER_MASS, PLAYER_RADIUS, PLAYER_SPEED},
+    environment::Tile,
+    health::{Durability, ProjectileKind},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum TankState {
     /// Stores clock in GameState ticks for shooting behavior.
     /// This is when the Tank cannot move directly after shooting.
@@ -12,10 +18,12 @@ pub enum TankState {
 }
 
 /// Data the Server has to track for a Player Tank
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Player {
     /// ID of the Player, taken from Client ID
     pub id: String,
+    /// Tanks only damage and target Tanks on other teams
+    pub team: usize,
     pub alive: bool,
     pub state: TankState,
     pub position: Vector2,
@@ -25,13 +33,33 @@ pub struct Player {
     pub movement_dir: f64,
     /// Normalized Vector for direction of movement of the Player
     pub movement: Vector2,
+    /// Angle the hull of the Tank is facing, which is the direction it last drove in
+    pub hull_angle: f64,
+    /// Distance the Tank travelled during the last tick
+    pub velocity: Vector2,
+    /// Heavier Tanks are pushed less when they bump into other Tanks
+    pub mass: f64,
     pub bullets_remaining: u8,
+    /// The kind of projectile the Tank fires
+    pub projectile: ProjectileKind,
+    pub hit_points: u32,
+    /// Hit points of the Tank when it is revived
+    pub max_hit_points: u32,
+    /// Reduces the damage of every hit on the Tank
+    pub armor: u32,
 }
 
 impl Player {
     pub const TURN_RATE: f64 = 0.01;
-    pub fn physics_update(&mut self) {
-        match self.state {
+    /// Fraction of the difference between the current and desired velocity
+    /// that a Tank can make up each tick while it is on ice
+    pub const ICE_GRIP: f64 = 0.03;
+
+    /// Move the Tank over the ground it is currently on
+    pub fn physics_update(&mut self, ground: Option<&Tile>) {
+        let on_ice = matches!(ground, Some(Tile::Ice));
+
+        let target = match self.state {
             TankState::Idle => {
                 // determine which way to rotate the movement based on the
                 // difference between the current movement and the desired movement direction
@@ -43,8 +71,12 @@ impl Player {
                 //             .signum(),
                 // );
 
-                // move the player by the movement vector
-                self.position = self.position.plus(&self.movement.scale(PLAYER_SPEED))
+                if self.movement.magnitude() > 0.0 {
+                    self.hull_angle = self.movement.y.atan2(self.movement.x);
+                }
+
+                self.movement
+                    .scale(PLAYER_SPEED * ground.map_or(1.0, Tile::speed_factor))
             }
             TankState::Shooting(ref mut dur) => {
                 if *dur > 0 {
@@ -52,22 +84,71 @@ impl Player {
                 } else {
                     self.state = TankState::Idle;
                 }
+
+                Vector2::zero()
             }
-        }
+        };
+
+        // ice only lets the Tank slowly change its velocity, everywhere else it is immediate
+        self.velocity = match on_ice {
+            true => self.velocity.plus(
+                &target
+                    .plus(&self.velocity.scale(-1.0))
+                    .scale(Self::ICE_GRIP),
+            ),
+            false => target,
+        };
+
+        // move the player by the velocity
+        self.position = self.position.plus(&self.velocity);
+    }
+
+    /// The square that the Tank occupies, which is what it collides with
+    pub fn hull(&self) -> OrientedBox {
+        OrientedBox::new(
+            self.position,
+            Vector2::new(PLAYER_RADIUS, PLAYER_RADIUS),
+            self.hull_angle,
+        )
     }
 }
 
 impl Player {
+    /// Bring the Tank back into the game with a full set of bullets and hit points
+    pub fn revive(&mut self) {
+        self.alive = true;
+        self.hit_points = self.max_hit_points;
+        self.velocity = Vector2::zero();
+        self.hull_angle = 0.0;
+        self.state = TankState::Idle;
+        self.bullets_remaining = BULLET_COUNT;
+    }
+
     pub fn new(id: String) -> Self {
         Self {
             id,
+            team: 0,
             alive: true,
             gun_angle: 0.0,
             state: TankState::Idle,
             position: Vector2::zero(),
             movement_dir: 0.0,
             movement: Vector2::zero(),
+            hull_angle: 0.0,
+            velocity: Vector2::zero(),
+            mass: PLAYER_MASS,
             bullets_remaining: BULLET_COUNT,
+            projectile: ProjectileKind::default(),
+            hit_points: Durability::FRAGILE.hit_points,
+            max_hit_points: Durability::FRAGILE.hit_points,
+            armor: Durability::FRAGILE.armor,
         }
     }
+
+    /// Change how much damage the Tank can take, restoring its hit points
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.hit_points = durability.hit_points;
+        self.max_hit_points = durability.hit_points;
+        self.armor = durability.armor;
+    }
 }
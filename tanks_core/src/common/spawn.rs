@@ -0,0 +1,177 @@
+//! Choosing where Tanks enter the map
+
+use crate::utils::{box_box_collision, OrientedBox, Vector2};
+
+use super::{
+    constants::{MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH, PLAYER_RADIUS},
+    gamestate::GameState,
+    player::Player,
+};
+
+/// A position a Tank could be placed at
+struct Candidate {
+    position: Vector2,
+    /// Whether the position came from the spawn points of the map rather than being a fallback
+    from_map: bool,
+}
+
+/// How suitable a candidate is, compared field by field with the most important first
+#[derive(PartialEq, PartialOrd)]
+struct Score {
+    /// No other Tank is in the way
+    unoccupied: bool,
+    /// Inside of the safe zone, if there is one
+    safe: bool,
+    from_map: bool,
+    /// Distance to the closest hostile Tank or bullet
+    threat_distance: f64,
+}
+
+impl GameState {
+    /// Add a Tank for a player to the game at a spawn point,
+    /// which starts out destroyed when there is no room for it on the map
+    pub fn add_player(&mut self, id: String) {
+        let mut player = Player::new(id.clone());
+        match self.spawn_point(&id) {
+            Some(position) => player.position = position,
+            None => player.alive = false,
+        }
+        player.set_durability(self.durability(&id));
+        self.players.insert(id, player);
+    }
+
+    /// Revive the Tank of a player and move it to a spawn point, if there is room for it
+    pub fn respawn(&mut self, id: &str) {
+        let Some(position) = self.spawn_point(id) else {
+            return;
+        };
+        let durability = self.durability(id);
+
+        if let Some(player) = self.players.get_mut(id) {
+            player.set_durability(durability);
+            player.revive();
+            player.position = position;
+        }
+    }
+
+    /// Respawn every Tank which is not controlled by a bot, such as when a new map is loaded
+    ///
+    /// Tanks are placed one at a time in order of their ids, so that they spread out
+    /// over the spawn points the same way regardless of the order of the players.
+    pub fn respawn_players(&mut self) {
+        let mut ids = self
+            .players
+            .keys()
+            .filter(|id| !self.bots.contains_key(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        // the old positions of the Tanks should not affect where they are placed
+        for id in &ids {
+            if let Some(player) = self.players.get_mut(id) {
+                player.alive = false;
+            }
+        }
+
+        for id in &ids {
+            self.respawn(id);
+        }
+    }
+
+    /// Where the Tank of a player should enter the map
+    ///
+    /// The spawn points of the map are used when possible, otherwise the centre of any tile
+    /// that is clear of walls. Points which are already taken by another Tank or outside of
+    /// the safe zone are avoided, and then the point furthest from hostile Tanks and bullets is chosen.
+    /// There is no spawn point when every tile of the map is blocked by walls.
+    pub fn spawn_point(&self, player_id: &str) -> Option<Vector2> {
+        let me = self.players.get(player_id);
+
+        let score = |candidate: &Candidate| {
+            let others = self
+                .players
+                .values()
+                .filter(|p| p.alive && p.id != player_id);
+
+            let hull = spawned_hull(&candidate.position);
+            let occupied = others
+                .clone()
+                .any(|p| box_box_collision(&p.hull(), &hull).is_err());
+
+            let safe = self
+                .zone
+                .as_ref()
+                .is_none_or(|zone| zone.is_position_safe(&candidate.position));
+
+            let threat_distance = others
+                .filter(|p| me.is_none_or(|me| self.are_hostile(me, p)))
+                .map(|p| p.position)
+                .chain(self.bullets.iter().map(|b| b.position))
+                .map(|position| distance(&position, &candidate.position))
+                .fold(f64::INFINITY, f64::min);
+
+            Score {
+                unoccupied: !occupied,
+                safe,
+                from_map: candidate.from_map,
+                threat_distance,
+            }
+        };
+
+        let mut best: Option<(Candidate, Score)> = None;
+        for candidate in self.spawn_candidates() {
+            let candidate_score = score(&candidate);
+
+            // only a strictly better score replaces an earlier candidate, so ties are stable
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_score)| candidate_score > *best_score)
+            {
+                best = Some((candidate, candidate_score));
+            }
+        }
+
+        best.map(|(candidate, _)| candidate.position)
+    }
+
+    /// The map spawn points followed by the centre of every tile, keeping only those
+    /// where a Tank would be inside of the playfield without overlapping a wall
+    fn spawn_candidates(&self) -> Vec<Candidate> {
+        let environment = &self.environment;
+
+        let from_map = environment.spawns.iter().map(|location| (*location, true));
+        let fallbacks = (0..MAP_BLOCK_HEIGHT)
+            .flat_map(|row| (0..MAP_BLOCK_WIDTH).map(move |col| ((row, col), false)));
+
+        from_map
+            .chain(fallbacks)
+            .filter(|((row, col), _)| *row < MAP_BLOCK_HEIGHT && *col < MAP_BLOCK_WIDTH)
+            .map(|((row, col), from_map)| Candidate {
+                position: Vector2::new(col as f64 + 0.5, row as f64 + 0.5),
+                from_map,
+            })
+            .filter(|candidate| {
+                let hull = spawned_hull(&candidate.position);
+                environment
+                    .tiles
+                    .iter()
+                    .filter(|(_, tile)| tile.blocks_tanks())
+                    .all(|((row, col), _)| {
+                        let tile = Vector2::new(*col as f64, *row as f64);
+                        box_box_collision(&OrientedBox::axis_aligned(&tile, 1.0, 1.0), &hull)
+                            .is_ok()
+                    })
+            })
+            .collect()
+    }
+}
+
+/// The hull of a Tank revived at a position, which always faces the same way
+fn spawned_hull(position: &Vector2) -> OrientedBox {
+    OrientedBox::new(*position, Vector2::new(PLAYER_RADIUS, PLAYER_RADIUS), 0.0)
+}
+
+fn distance(a: &Vector2, b: &Vector2) -> f64 {
+    a.plus(&b.scale(-1.0)).magnitude()
+}
@@ -0,0 +1,94 @@
+//! The contracting safe area used to force Tanks together in a battle royale
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{Rng, Vector2};
+
+use super::{
+    constants::{MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH},
+    gamestate::GameState,
+};
+
+/// Circle of tiles which are safe to stand on.
+/// Every tile whose center lies outside of the circle is deadly.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SafeZone {
+    pub center: Vector2,
+    pub radius: f64,
+    /// Radius that the zone contracts to once the countdown finishes
+    pub next_radius: f64,
+    /// Ticks until the zone starts contracting again
+    pub countdown: u32,
+}
+
+impl SafeZone {
+    /// Ticks that the zone holds its size between contractions
+    pub const HOLD_TICKS: u32 = 15 * 60;
+    /// Distance the radius shrinks each tick while contracting
+    pub const SHRINK_RATE: f64 = 0.01;
+    /// Fraction of the radius that remains after each contraction
+    pub const SHRINK_FACTOR: f64 = 0.6;
+
+    /// Create a zone covering the whole map around a center picked from the seed
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+
+        let (width, height) = (MAP_BLOCK_WIDTH as f64, MAP_BLOCK_HEIGHT as f64);
+        let center = Vector2::new(
+            rng.range(width * 0.25, width * 0.75),
+            rng.range(height * 0.25, height * 0.75),
+        );
+        let radius = (width * width + height * height).sqrt();
+
+        Self {
+            center,
+            radius,
+            next_radius: (width.min(height) / 2.0) * Self::SHRINK_FACTOR,
+            countdown: Self::HOLD_TICKS,
+        }
+    }
+
+    /// Whether the center of the tile at (row, column) is inside of the zone
+    pub fn is_tile_safe(&self, row: usize, col: usize) -> bool {
+        let tile_center = Vector2::new(col as f64 + 0.5, row as f64 + 0.5);
+        tile_center.plus(&self.center.scale(-1.0)).magnitude() <= self.radius
+    }
+
+    /// Whether the tile underneath the position is inside of the zone
+    pub fn is_position_safe(&self, position: &Vector2) -> bool {
+        self.is_tile_safe(position.y.max(0.0) as usize, position.x.max(0.0) as usize)
+    }
+
+    pub fn is_contracting(&self) -> bool {
+        self.countdown == 0
+    }
+
+    /// Count down to the next contraction, or keep shrinking when it has begun
+    pub fn tick(&mut self) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+        } else if self.radius > self.next_radius {
+            self.radius = (self.radius - Self::SHRINK_RATE).max(self.next_radius);
+        } else {
+            self.next_radius *= Self::SHRINK_FACTOR;
+            self.countdown = Self::HOLD_TICKS;
+        }
+    }
+}
+
+impl GameState {
+    /// Shrink the zone and eliminate every Tank standing outside of it
+    pub(crate) fn apply_zone(&mut self) {
+        let Some(zone) = &mut self.zone else {
+            return;
+        };
+
+        zone.tick();
+
+        for player in self.players.values_mut().filter(|p| p.alive) {
+            if !zone.is_position_safe(&player.position) {
+                player.alive = false;
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Seeded generation of playable arenas, so that a random map can be recreated from its seed
+
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+
+use crate::{
+    assets::write_environment,
+    common::{
+        constants::{MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH},
+        environment::{neighbours, Environment, MapMetadata, MapMode, Tile},
+    },
+    utils::Rng,
+};
+
+/// How the halves of a generated map mirror each other so that no spawn has an advantage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    /// The left half mirrors the right half
+    Horizontal,
+    /// The top half mirrors the bottom half
+    Vertical,
+    /// The map looks the same after turning it 180 degrees
+    Rotational,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    /// Fraction of the tiles that should be walls, from 0 to 1
+    pub wall_density: f64,
+    /// Fraction of the walls that can be destroyed, from 0 to 1
+    pub destructable_ratio: f64,
+    pub symmetry: Symmetry,
+    /// Number of spawn points, rounded up to an even number for symmetric maps
+    pub spawns: usize,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            width: MAP_BLOCK_WIDTH,
+            height: MAP_BLOCK_HEIGHT,
+            wall_density: 0.2,
+            destructable_ratio: 0.4,
+            symmetry: Symmetry::Rotational,
+            spawns: 4,
+        }
+    }
+}
+
+impl GeneratorConfig {
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// The tile that mirrors (row, column) under the symmetry of the map
+    fn partner(&self, (row, col): (usize, usize)) -> (usize, usize) {
+        match self.symmetry {
+            Symmetry::None => (row, col),
+            Symmetry::Horizontal => (row, self.width - 1 - col),
+            Symmetry::Vertical => (self.height - 1 - row, col),
+            Symmetry::Rotational => (self.height - 1 - row, self.width - 1 - col),
+        }
+    }
+
+    /// Build a new arena from the configuration.
+    /// The same configuration always produces the same arena.
+    pub fn generate(&self) -> Environment {
+        let mut rng = Rng::new(self.seed);
+        let mut environment = Environment {
+            width: self.width,
+            height: self.height,
+            metadata: MapMetadata {
+                author: Some(format!("generator (seed {})", self.seed)),
+                players: None,
+                mode: Some(MapMode::Versus),
+            },
+            ..Default::default()
+        };
+
+        if self.width == 0 || self.height == 0 {
+            return environment;
+        }
+
+        environment.spawns = self.place_spawns(&mut rng);
+        environment.metadata.players = Some(environment.spawns.len());
+
+        // spawns keep a ring of open ground around them
+        let cleared = environment
+            .spawns
+            .iter()
+            .flat_map(|&(row, col)| {
+                (row.saturating_sub(1)..=row + 1)
+                    .flat_map(move |r| (col.saturating_sub(1)..=col + 1).map(move |c| (r, c)))
+            })
+            .collect::<HashSet<_>>();
+
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let location = (row, col);
+                let partner = self.partner(location);
+
+                // only decide each mirrored pair once
+                if partner < location || cleared.contains(&location) || cleared.contains(&partner) {
+                    continue;
+                }
+
+                if rng.next_f64() < self.wall_density {
+                    let elevation = 1 + rng.below(3);
+                    let tile = match rng.next_f64() < self.destructable_ratio {
+                        true => Tile::DesructableWall((3, elevation)),
+                        false => Tile::IndestructableWall(elevation),
+                    };
+
+                    environment.tiles.insert(partner, tile.clone());
+                    environment.tiles.insert(location, tile);
+                }
+            }
+        }
+
+        self.connect_spawns(&mut environment);
+
+        environment
+    }
+
+    /// Pick spawn points that are spread apart from each other, moving them closer together
+    /// when there is no room left at the full spacing
+    fn place_spawns(&self, rng: &mut Rng) -> Vec<(usize, usize)> {
+        const MIN_SPACING: usize = 4;
+        const ATTEMPTS: usize = 100;
+
+        let mut spawns: Vec<(usize, usize)> = Vec::new();
+        let mut spacing = MIN_SPACING;
+
+        while spawns.len() < self.spawns {
+            let fits = |candidate: (usize, usize)| {
+                let distance =
+                    |(r, c): (usize, usize)| r.abs_diff(candidate.0) + c.abs_diff(candidate.1);
+
+                // symmetric maps also need the mirrored spawn to be a distinct tile
+                spawns.iter().all(|&spawn| distance(spawn) >= spacing)
+                    && (self.symmetry == Symmetry::None
+                        || distance(self.partner(candidate)) >= spacing)
+            };
+
+            let found = (0..ATTEMPTS)
+                .map(|_| (rng.below(self.height), rng.below(self.width)))
+                .find(|&candidate| fits(candidate));
+
+            let Some(candidate) = found else {
+                // spawns can be brought as close as neighbouring tiles, but never share one
+                match spacing {
+                    1 => break,
+                    _ => spacing -= 1,
+                }
+                continue;
+            };
+
+            spawns.push(candidate);
+            if self.symmetry != Symmetry::None {
+                spawns.push(self.partner(candidate));
+            }
+        }
+
+        spawns
+    }
+
+    /// Knock down walls until every spawn can drive to the first one
+    fn connect_spawns(&self, environment: &mut Environment) {
+        let Some(&origin) = environment.spawns.first() else {
+            return;
+        };
+
+        loop {
+            let reachable = environment.reachable_from(origin);
+
+            let Some(&stranded) = environment
+                .spawns
+                .iter()
+                .find(|spawn| !reachable.contains(spawn))
+            else {
+                return;
+            };
+
+            // shortest route from the stranded spawn to any reachable tile, ignoring walls
+            for location in self.route_to(stranded, &reachable) {
+                environment.tiles.remove(&location);
+                environment.tiles.remove(&self.partner(location));
+            }
+        }
+    }
+
+    fn route_to(
+        &self,
+        start: (usize, usize),
+        targets: &HashSet<(usize, usize)>,
+    ) -> Vec<(usize, usize)> {
+        let mut previous = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        previous.insert(start, start);
+
+        while let Some(location) = queue.pop_front() {
+            if targets.contains(&location) {
+                let mut route = vec![location];
+                let mut current = location;
+                while current != start {
+                    current = previous[&current];
+                    route.push(current);
+                }
+                return route;
+            }
+
+            for next in neighbours(location, self.width, self.height) {
+                if let Entry::Vacant(entry) = previous.entry(next) {
+                    entry.insert(location);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        Vec::new()
+    }
+}
+
+/// Generate an arena and write it out as the text of a MapData file
+pub fn generate_map_file(name: &str, config: &GeneratorConfig) -> String {
+    write_environment(name, &config.generate())
+}
@@ -1,3 +1,9 @@
 pub mod assets;
 pub mod common;
+pub mod generator;
+pub mod modes;
+pub mod replay;
+pub mod snapshot;
+pub mod training;
 pub mod utils;
+pub mod validation;
@@ -0,0 +1,164 @@
+//! Single player (or co-op) progression through an ordered list of levels
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{environment::Environment, gamestate::GameState},
+    utils::Vector2,
+};
+
+use super::ModeEvent;
+
+/// A single stage in a campaign.
+/// The enemy roster comes from the placements in the environment
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Level {
+    pub name: String,
+    pub environment: Environment,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Campaign {
+    levels: Vec<Level>,
+    /// Index of the level currently being played
+    current: usize,
+    /// Lives shared between every player in the party
+    lives: u32,
+    started: bool,
+    finished: bool,
+}
+
+impl Campaign {
+    pub const STARTING_LIVES: u32 = 3;
+
+    pub fn new(levels: Vec<Level>) -> Self {
+        Self {
+            levels,
+            current: 0,
+            lives: Self::STARTING_LIVES,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Build a campaign from an ordered list of map names,
+    /// failing with the name of the first map that could not be found
+    pub fn from_maps(
+        names: &[String],
+        maps: &HashMap<String, Environment>,
+    ) -> Result<Self, String> {
+        names
+            .iter()
+            .map(|name| match maps.get(name) {
+                Some(environment) => Ok(Level {
+                    name: name.clone(),
+                    environment: environment.clone(),
+                }),
+                None => Err(name.clone()),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::new)
+    }
+
+    pub fn current_level(&self) -> usize {
+        self.current
+    }
+
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn update(&mut self, gamestate: &mut GameState) -> Vec<ModeEvent> {
+        let mut events = Vec::new();
+
+        if self.finished {
+            return events;
+        }
+
+        if !self.started {
+            self.started = true;
+            if self.levels.is_empty() {
+                self.finished = true;
+                events.push(ModeEvent::CampaignComplete);
+            } else {
+                self.load_level(gamestate, &mut events);
+            }
+            return events;
+        }
+
+        let humans = gamestate
+            .players
+            .values()
+            .filter(|p| !gamestate.bots.contains_key(&p.id))
+            .collect::<Vec<_>>();
+
+        // wait for someone to join the party before the level can be won or lost
+        if humans.is_empty() {
+            return events;
+        }
+
+        if gamestate.bots_remaining() == 0 {
+            events.push(ModeEvent::MissionComplete {
+                level: self.current,
+            });
+
+            if self.current + 1 < self.levels.len() {
+                self.current += 1;
+                self.load_level(gamestate, &mut events);
+            } else {
+                self.finished = true;
+                events.push(ModeEvent::CampaignComplete);
+            }
+        } else if humans.iter().all(|p| !p.alive) {
+            self.lives = self.lives.saturating_sub(1);
+
+            if self.lives > 0 {
+                events.push(ModeEvent::LifeLost { lives: self.lives });
+                self.load_level(gamestate, &mut events);
+            } else {
+                self.finished = true;
+                events.push(ModeEvent::CampaignFailed {
+                    level: self.current,
+                });
+            }
+        }
+
+        events
+    }
+
+    /// Replace the environment and bots with those of the current level
+    /// and give every player in the party a fresh Tank
+    fn load_level(&self, gamestate: &mut GameState, events: &mut Vec<ModeEvent>) {
+        let level = &self.levels[self.current];
+
+        gamestate.clear_bots();
+        gamestate.bullets.clear();
+        gamestate.environment = level.environment.clone();
+        // the party fights alongside each other against the bots
+        gamestate.teams = true;
+
+        gamestate.respawn_players();
+
+        for (i, enemy) in level.environment.enemies.iter().enumerate() {
+            let (row, col) = enemy.location;
+            gamestate.spawn_bot(
+                format!("{}-{}", level.name, i),
+                enemy.archetype,
+                Vector2::new(col as f64 + 0.5, row as f64 + 0.5),
+            );
+        }
+
+        events.push(ModeEvent::MapLoaded);
+        events.push(ModeEvent::LevelStarted {
+            level: self.current,
+            name: level.name.clone(),
+            lives: self.lives,
+        });
+    }
+}
@@ -0,0 +1,94 @@
+//! Rulesets layered on top of a [`GameState`] that decide how a session progresses
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::gamestate::GameState;
+
+pub mod campaign;
+pub mod rotation;
+pub mod royale;
+pub mod survival;
+
+use campaign::Campaign;
+use rotation::Rotation;
+use royale::BattleRoyale;
+use survival::Survival;
+
+/// The ruleset that a session is being played under
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub enum GameMode {
+    /// Free play between whoever is in the session
+    #[default]
+    Versus,
+    /// Sequential levels of bot enemies
+    Campaign(Campaign),
+    /// Endless waves of bots
+    Survival(Box<Survival>),
+    /// Last Tank standing in a shrinking arena
+    BattleRoyale(BattleRoyale),
+    /// Versus matches which move on to a new map once a winner is decided
+    Rotation(Box<Rotation>),
+}
+
+impl GameMode {
+    /// Advance the ruleset after looking at the current GameState
+    ///
+    /// This should be run once per tick, before [`GameState::tick`]
+    pub fn update(&mut self, gamestate: &mut GameState) -> Vec<ModeEvent> {
+        match self {
+            GameMode::Versus => Vec::new(),
+            GameMode::Campaign(campaign) => campaign.update(gamestate),
+            GameMode::Survival(survival) => survival.update(gamestate),
+            GameMode::BattleRoyale(royale) => royale.update(gamestate),
+            GameMode::Rotation(rotation) => rotation.update(gamestate),
+        }
+    }
+}
+
+/// Notable moments in a ruleset that the players should be told about
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ModeEvent {
+    /// The environment in the GameState was replaced and should be redrawn
+    MapLoaded,
+    /// A new environment has been loaded into the GameState
+    LevelStarted {
+        level: usize,
+        name: String,
+        lives: u32,
+    },
+    /// Every bot in the level has been destroyed
+    MissionComplete { level: usize },
+    /// Every player in the level has been destroyed and the level restarts
+    LifeLost { lives: u32 },
+    /// The last level of the campaign was completed
+    CampaignComplete,
+    /// The party ran out of lives
+    CampaignFailed { level: usize },
+    /// A new wave of bots has entered the map
+    WaveStarted { wave: u32 },
+    /// The number of bots left in the current wave has changed
+    WaveStatus {
+        wave: u32,
+        remaining: usize,
+        highest_wave: u32,
+    },
+    /// Every bot in the wave was destroyed and the intermission begins
+    WaveCleared { wave: u32 },
+    /// The party was overrun and the waves start over
+    SurvivalOver { wave: u32, highest_wave: u32 },
+    /// Everyone has been revived and a new safe zone was chosen
+    RoyaleStarted { round: u64 },
+    /// The round ended with at most one Tank left standing
+    RoyaleWinner { player: Option<String> },
+    /// A match began on the named map, or on the previous map when there was none to load
+    MatchStarted { map: Option<String> },
+    /// The match ended with at most one Tank left standing
+    MatchEnded { winner: Option<String> },
+    /// Players have the given number of ticks to vote for the map of the next match
+    VoteStarted { candidates: Vec<String>, ticks: u32 },
+    /// The number of votes for each candidate changed
+    VoteStatus {
+        votes: Vec<(String, usize)>,
+        ticks: u32,
+    },
+}
@@ -0,0 +1,236 @@
+//! Back to back versus matches, moving on to a new map after each one ends
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{environment::Environment, gamestate::GameState},
+    utils::Rng,
+};
+
+use super::ModeEvent;
+
+/// How the map for the next match is chosen
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum MapSelection {
+    /// Play the maps in order, starting over after the last one
+    Playlist(Vec<String>),
+    /// Let the players vote between this many maps picked at random
+    Vote { candidates: usize },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+enum Phase {
+    Playing,
+    /// Ticks left before the next map is loaded
+    MatchOver(u32),
+    Voting {
+        candidates: Vec<String>,
+        /// The map chosen by each player that has voted
+        ballots: HashMap<String, String>,
+        /// Ticks left before the votes are counted
+        ticks: u32,
+        /// Whether a ballot was cast since the tally was last reported
+        changed: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rotation {
+    /// Every map that can be played, by name
+    maps: BTreeMap<String, Environment>,
+    selection: MapSelection,
+    /// Index of the next map in the playlist
+    position: usize,
+    phase: Phase,
+    started: bool,
+}
+
+impl Rotation {
+    /// Length of the break after a match is decided, in GameState ticks
+    pub const MATCH_BREAK_TICKS: u32 = 5 * 60;
+    /// Length of a vote for the next map, in GameState ticks
+    pub const VOTE_TICKS: u32 = 15 * 60;
+
+    /// Matches on the given maps, where the maps are picked using the RNG of the GameState
+    /// when there is no playlist. Playlist entries that are not in the maps are skipped.
+    pub fn new(maps: HashMap<String, Environment>, selection: MapSelection) -> Self {
+        let selection = match selection {
+            MapSelection::Playlist(names) => MapSelection::Playlist(
+                names
+                    .into_iter()
+                    .filter(|name| maps.contains_key(name))
+                    .collect(),
+            ),
+            vote => vote,
+        };
+
+        Self {
+            maps: maps.into_iter().collect(),
+            selection,
+            position: 0,
+            phase: Phase::Playing,
+            started: false,
+        }
+    }
+
+    /// Record the map a player wants to play next,
+    /// returning whether there is a vote running which includes that map
+    pub fn vote(&mut self, player: &str, map: &str) -> bool {
+        match &mut self.phase {
+            Phase::Voting {
+                candidates,
+                ballots,
+                changed,
+                ..
+            } if candidates.iter().any(|c| c == map) => {
+                ballots.insert(player.to_string(), map.to_string());
+                *changed = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self, gamestate: &mut GameState) -> Vec<ModeEvent> {
+        let mut events = Vec::new();
+
+        if !self.started {
+            self.started = true;
+            let first = self.next_map(&mut gamestate.rng);
+            self.load_map(first, gamestate, &mut events);
+            return events;
+        }
+
+        match &mut self.phase {
+            Phase::Playing => {
+                let alive = gamestate
+                    .players
+                    .values()
+                    .filter(|p| p.alive)
+                    .collect::<Vec<_>>();
+
+                // a lone Tank keeps playing until someone else joins
+                if alive.len() <= 1 && (gamestate.players.len() > 1 || alive.is_empty()) {
+                    events.push(ModeEvent::MatchEnded {
+                        winner: alive.first().map(|p| p.id.clone()),
+                    });
+                    self.end_match(&mut gamestate.rng, &mut events);
+                }
+            }
+            Phase::MatchOver(0) => {
+                let next = self.next_map(&mut gamestate.rng);
+                self.load_map(next, gamestate, &mut events);
+            }
+            Phase::MatchOver(ticks) => *ticks -= 1,
+            Phase::Voting {
+                candidates,
+                ballots,
+                ticks: 0,
+                ..
+            } => {
+                let winner = tally(candidates, ballots)
+                    .into_iter()
+                    // searching backwards lets the earliest candidate win a tie
+                    .rev()
+                    .max_by_key(|(_, votes)| *votes)
+                    .map(|(map, _)| map);
+
+                self.load_map(winner, gamestate, &mut events);
+            }
+            Phase::Voting {
+                candidates,
+                ballots,
+                ticks,
+                changed,
+            } => {
+                *ticks -= 1;
+
+                if *changed {
+                    *changed = false;
+                    events.push(ModeEvent::VoteStatus {
+                        votes: tally(candidates, ballots),
+                        ticks: *ticks,
+                    });
+                }
+            }
+        }
+
+        events
+    }
+
+    /// Move on from a finished match, either straight to the next map or to a vote for it
+    fn end_match(&mut self, rng: &mut Rng, events: &mut Vec<ModeEvent>) {
+        self.phase = match self.selection {
+            MapSelection::Vote { candidates } if self.maps.len() > 1 => {
+                let mut pool = self.maps.keys().cloned().collect::<Vec<_>>();
+                let mut picked = Vec::new();
+                while picked.len() < candidates.max(1) && !pool.is_empty() {
+                    picked.push(pool.swap_remove(rng.below(pool.len())));
+                }
+
+                events.push(ModeEvent::VoteStarted {
+                    candidates: picked.clone(),
+                    ticks: Self::VOTE_TICKS,
+                });
+
+                Phase::Voting {
+                    candidates: picked,
+                    ballots: HashMap::new(),
+                    ticks: Self::VOTE_TICKS,
+                    changed: false,
+                }
+            }
+            _ => Phase::MatchOver(Self::MATCH_BREAK_TICKS),
+        };
+    }
+
+    /// The map that comes after the current one when nobody gets a say
+    fn next_map(&mut self, rng: &mut Rng) -> Option<String> {
+        match &self.selection {
+            MapSelection::Playlist(names) if !names.is_empty() => {
+                let name = names[self.position % names.len()].clone();
+                self.position = (self.position + 1) % names.len();
+                Some(name)
+            }
+            MapSelection::Playlist(_) => None,
+            MapSelection::Vote { .. } => {
+                let index = rng.below(self.maps.len());
+                self.maps.keys().nth(index).cloned()
+            }
+        }
+    }
+
+    /// Restart the round on the named map, keeping the current one if there is no map to load
+    fn load_map(
+        &mut self,
+        name: Option<String>,
+        gamestate: &mut GameState,
+        events: &mut Vec<ModeEvent>,
+    ) {
+        self.phase = Phase::Playing;
+
+        gamestate.bullets.clear();
+
+        if let Some(environment) = name.as_ref().and_then(|name| self.maps.get(name)) {
+            gamestate.environment = environment.clone();
+            events.push(ModeEvent::MapLoaded);
+        }
+
+        gamestate.respawn_players();
+
+        events.push(ModeEvent::MatchStarted { map: name });
+    }
+}
+
+/// Number of ballots cast for each candidate, in the order of the candidates
+fn tally(candidates: &[String], ballots: &HashMap<String, String>) -> Vec<(String, usize)> {
+    candidates
+        .iter()
+        .map(|candidate| {
+            let votes = ballots.values().filter(|map| *map == candidate).count();
+            (candidate.clone(), votes)
+        })
+        .collect()
+}
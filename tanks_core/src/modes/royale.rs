@@ -0,0 +1,87 @@
+//! Last Tank standing inside of a contracting safe zone
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{gamestate::GameState, zone::SafeZone};
+
+use super::ModeEvent;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+enum Phase {
+    Playing,
+    /// Ticks left before the next round begins
+    RoundOver(u32),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BattleRoyale {
+    round: u64,
+    phase: Phase,
+    started: bool,
+}
+
+impl Default for BattleRoyale {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BattleRoyale {
+    /// Length of the break after a winner is decided, in GameState ticks
+    pub const ROUND_BREAK_TICKS: u32 = 5 * 60;
+
+    pub fn new() -> Self {
+        Self {
+            round: 0,
+            phase: Phase::Playing,
+            started: false,
+        }
+    }
+
+    pub fn update(&mut self, gamestate: &mut GameState) -> Vec<ModeEvent> {
+        let mut events = Vec::new();
+
+        if !self.started {
+            self.started = true;
+            self.start_round(gamestate, &mut events);
+            return events;
+        }
+
+        match self.phase {
+            Phase::Playing => {
+                let alive = gamestate
+                    .players
+                    .values()
+                    .filter(|p| p.alive)
+                    .collect::<Vec<_>>();
+
+                // a lone Tank keeps playing until someone else joins or the zone catches it
+                if alive.len() <= 1 && (gamestate.players.len() > 1 || alive.is_empty()) {
+                    events.push(ModeEvent::RoyaleWinner {
+                        player: alive.first().map(|p| p.id.clone()),
+                    });
+                    self.phase = Phase::RoundOver(Self::ROUND_BREAK_TICKS);
+                }
+            }
+            Phase::RoundOver(0) => {
+                self.round += 1;
+                self.start_round(gamestate, &mut events);
+            }
+            Phase::RoundOver(ref mut ticks) => *ticks -= 1,
+        }
+
+        events
+    }
+
+    fn start_round(&mut self, gamestate: &mut GameState, events: &mut Vec<ModeEvent>) {
+        self.phase = Phase::Playing;
+
+        gamestate.bullets.clear();
+        gamestate.zone = Some(SafeZone::new(gamestate.rng.next_u64()));
+        gamestate.respawn_players();
+
+        events.push(ModeEvent::RoyaleStarted {
+            round: self.round + 1,
+        });
+    }
+}
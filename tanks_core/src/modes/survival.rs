@@ -0,0 +1,184 @@
+//! Endless waves of bots which grow larger and tougher until the party is overrun
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{
+        bot::BotArchetype,
+        constants::{MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH},
+        environment::Environment,
+        gamestate::GameState,
+    },
+    utils::Vector2,
+};
+
+use super::ModeEvent;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+enum Phase {
+    /// Ticks left in the break before the next wave spawns
+    Intermission(u32),
+    Fighting,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Survival {
+    environment: Environment,
+    /// The wave currently being fought, starting from 1
+    wave: u32,
+    /// The furthest wave that the session has reached
+    highest_wave: u32,
+    phase: Phase,
+    /// Last remaining count that was reported, so that updates are only sent on change
+    reported_remaining: Option<usize>,
+    started: bool,
+}
+
+impl Survival {
+    /// Length of the break between waves, in GameState ticks
+    pub const INTERMISSION_TICKS: u32 = 5 * 60;
+    /// Number of bots in the first wave
+    pub const BASE_WAVE_SIZE: usize = 2;
+
+    pub fn new(environment: Environment) -> Self {
+        Self {
+            environment,
+            wave: 0,
+            highest_wave: 0,
+            phase: Phase::Intermission(Self::INTERMISSION_TICKS),
+            reported_remaining: None,
+            started: false,
+        }
+    }
+
+    pub fn wave(&self) -> u32 {
+        self.wave
+    }
+
+    pub fn highest_wave(&self) -> u32 {
+        self.highest_wave
+    }
+
+    pub fn update(&mut self, gamestate: &mut GameState) -> Vec<ModeEvent> {
+        let mut events = Vec::new();
+
+        if !self.started {
+            self.started = true;
+            gamestate.clear_bots();
+            gamestate.bullets.clear();
+            gamestate.environment = self.environment.clone();
+            // the party fights alongside each other against the bots
+            gamestate.teams = true;
+            events.push(ModeEvent::MapLoaded);
+        }
+
+        let humans = gamestate
+            .players
+            .values()
+            .filter(|p| !gamestate.bots.contains_key(&p.id))
+            .collect::<Vec<_>>();
+
+        // hold the first wave until someone has joined
+        if humans.is_empty() {
+            return events;
+        }
+
+        match self.phase {
+            Phase::Intermission(0) => {
+                self.wave += 1;
+                self.highest_wave = self.highest_wave.max(self.wave);
+                self.phase = Phase::Fighting;
+                self.spawn_wave(gamestate);
+                events.push(ModeEvent::WaveStarted { wave: self.wave });
+            }
+            Phase::Intermission(ref mut ticks) => *ticks -= 1,
+            Phase::Fighting if humans.iter().all(|p| !p.alive) => {
+                events.push(ModeEvent::SurvivalOver {
+                    wave: self.wave,
+                    highest_wave: self.highest_wave,
+                });
+                // start over from the beginning once everyone has been revived
+                self.wave = 0;
+                gamestate.clear_bots();
+                gamestate.bullets.clear();
+                self.begin_intermission(gamestate);
+            }
+            Phase::Fighting if gamestate.bots_remaining() == 0 => {
+                events.push(ModeEvent::WaveCleared { wave: self.wave });
+                self.begin_intermission(gamestate);
+            }
+            Phase::Fighting => (),
+        }
+
+        let remaining = gamestate.bots_remaining();
+        if self.reported_remaining != Some(remaining) {
+            self.reported_remaining = Some(remaining);
+            events.push(ModeEvent::WaveStatus {
+                wave: self.wave,
+                remaining,
+                highest_wave: self.highest_wave,
+            });
+        }
+
+        events
+    }
+
+    /// Players that were destroyed get to rejoin during the break
+    fn begin_intermission(&mut self, gamestate: &mut GameState) {
+        self.phase = Phase::Intermission(Self::INTERMISSION_TICKS);
+
+        let mut fallen = gamestate
+            .players
+            .values()
+            .filter(|p| !p.alive && !gamestate.bots.contains_key(&p.id))
+            .map(|p| p.id.clone())
+            .collect::<Vec<_>>();
+        fallen.sort();
+
+        for id in &fallen {
+            gamestate.respawn(id);
+        }
+    }
+
+    fn spawn_wave(&self, gamestate: &mut GameState) {
+        gamestate.clear_bots();
+
+        let spawners = self.spawn_locations();
+        let size = Self::BASE_WAVE_SIZE + self.wave as usize - 1;
+
+        for i in 0..size {
+            let (row, col) = spawners[i % spawners.len()];
+            gamestate.spawn_bot(
+                format!("wave{}-{}", self.wave, i),
+                Self::archetype_for(self.wave, i),
+                Vector2::new(col as f64 + 0.5, row as f64 + 0.5),
+            );
+        }
+    }
+
+    /// Tougher archetypes are mixed in as the waves progress
+    fn archetype_for(wave: u32, index: usize) -> BotArchetype {
+        const ARCHETYPES: [BotArchetype; 3] = [
+            BotArchetype::Rookie,
+            BotArchetype::Gunner,
+            BotArchetype::Hunter,
+        ];
+
+        let unlocked = (wave as usize).div_ceil(2).clamp(1, ARCHETYPES.len());
+        ARCHETYPES[(index + wave as usize) % unlocked]
+    }
+
+    /// The map defined spawners, or the corners of the map when there are none
+    fn spawn_locations(&self) -> Vec<(usize, usize)> {
+        if self.environment.spawners.is_empty() {
+            vec![
+                (0, 0),
+                (0, MAP_BLOCK_WIDTH - 1),
+                (MAP_BLOCK_HEIGHT - 1, 0),
+                (MAP_BLOCK_HEIGHT - 1, MAP_BLOCK_WIDTH - 1),
+            ]
+        } else {
+            self.environment.spawners.clone()
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Recordings of sessions as their starting state and the inputs applied on every tick,
+//! which play back exactly as they happened because the simulation is deterministic
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::gamestate::GameState,
+    modes::{GameMode, ModeEvent},
+    snapshot::SessionSnapshot,
+    utils::Vector2,
+};
+
+/// Bytes at the start of every replay file
+const REPLAY_MAGIC: &[u8; 4] = b"TNKR";
+/// Version of the replay format written by [`Replay::to_bytes`]
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+/// An action taken by a player which changes the simulation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PlayerInput {
+    /// Drive in a direction, or stop when it is zero
+    Move(Vector2),
+    /// Point the gun at an angle
+    Aim(f64),
+    Shoot,
+    /// Enter the session, getting a Tank if the player does not already have one
+    Join,
+    /// Vote for the map of the next match
+    Vote(String),
+    /// Leave the session for good, removing the Tank of the player
+    Leave,
+}
+
+impl PlayerInput {
+    /// Carry out the input for a player, which is the same for live sessions and replays
+    pub fn apply(&self, player: &str, gamestate: &mut GameState, mode: &mut GameMode) {
+        match self {
+            PlayerInput::Move(direction) => gamestate.set_player_movement(player, direction),
+            PlayerInput::Aim(angle) => gamestate.set_player_angle(player, *angle),
+            PlayerInput::Shoot => gamestate.player_shoot(player),
+            PlayerInput::Join => {
+                if !gamestate.players.contains_key(player) {
+                    gamestate.add_player(player.to_string());
+                }
+            }
+            PlayerInput::Vote(map) => {
+                if let GameMode::Rotation(rotation) = mode {
+                    rotation.vote(player, map);
+                }
+            }
+            PlayerInput::Leave => {
+                gamestate.players.remove(player);
+            }
+        }
+    }
+}
+
+/// An input that was applied before a tick of the GameState
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordedInput {
+    /// The tick count of the GameState when the input was applied
+    pub tick: u64,
+    /// Index of the player in [`Replay::players`]
+    pub player: u32,
+    pub input: PlayerInput,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    /// The session when the recording started
+    pub initial: SessionSnapshot,
+    /// Ids of every player that made an input, so that each input only stores an index
+    pub players: Vec<String>,
+    /// Inputs in the order they were applied
+    pub inputs: Vec<RecordedInput>,
+    /// The tick count of the GameState when the recording ended
+    pub final_tick: u64,
+    /// [`GameState::checksum`] when the recording ended
+    pub checksum: u64,
+}
+
+/// Records the inputs of a session from the moment it is created
+#[derive(Debug)]
+pub struct ReplayRecorder {
+    initial: SessionSnapshot,
+    players: Vec<String>,
+    inputs: Vec<RecordedInput>,
+}
+
+impl ReplayRecorder {
+    pub fn new(gamestate: &GameState, mode: &GameMode) -> Self {
+        Self {
+            initial: SessionSnapshot::new(gamestate, mode),
+            players: Vec::new(),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Remember an input that is applied before the next tick of the GameState
+    pub fn record(&mut self, gamestate: &GameState, player: &str, input: PlayerInput) {
+        let index = match self.players.iter().position(|p| p == player) {
+            Some(index) => index,
+            None => {
+                self.players.push(player.to_string());
+                self.players.len() - 1
+            }
+        };
+
+        self.inputs.push(RecordedInput {
+            tick: gamestate.ticks,
+            player: index as u32,
+            input,
+        });
+    }
+
+    /// The tick count of the GameState when the recording started
+    pub fn start_tick(&self) -> u64 {
+        self.initial.gamestate.ticks
+    }
+
+    /// End the recording at the current state of the session
+    pub fn finish(self, gamestate: &GameState) -> Replay {
+        Replay {
+            initial: self.initial,
+            players: self.players,
+            inputs: self.inputs,
+            final_tick: gamestate.ticks,
+            checksum: gamestate.checksum(),
+        }
+    }
+}
+
+impl Replay {
+    /// Encode the replay into the compact binary format of a replay file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bytes.extend(REPLAY_FORMAT_VERSION.to_le_bytes());
+        bytes.extend(postcard::to_stdvec(self).expect("replays can always be encoded"));
+        bytes
+    }
+
+    /// Decode the contents of a replay file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let body = bytes
+            .strip_prefix(REPLAY_MAGIC)
+            .ok_or(ReplayError::NotAReplay)?;
+
+        let (version, body) = body.split_at_checked(4).ok_or(ReplayError::NotAReplay)?;
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        if version != REPLAY_FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let replay =
+            postcard::from_bytes::<Self>(body).map_err(|e| ReplayError::Corrupt(e.to_string()))?;
+        replay.validate()?;
+        Ok(replay)
+    }
+
+    /// Check that the recording makes sense, so that playing it back cannot go wrong
+    /// for a replay that was damaged or put together by hand
+    pub fn validate(&self) -> Result<(), ReplayError> {
+        let start_tick = self.initial.gamestate.ticks;
+        if self.final_tick < start_tick {
+            return Err(ReplayError::Corrupt(format!(
+                "ends on tick {} before it starts on tick {}",
+                self.final_tick, start_tick
+            )));
+        }
+
+        if let Some(input) = self
+            .inputs
+            .iter()
+            .find(|input| input.player as usize >= self.players.len())
+        {
+            return Err(ReplayError::Corrupt(format!(
+                "input on tick {} is from player {}, but there are only {} players",
+                input.tick,
+                input.player,
+                self.players.len()
+            )));
+        }
+
+        if let Some(pair) = self
+            .inputs
+            .windows(2)
+            .find(|pair| pair[0].tick > pair[1].tick)
+        {
+            return Err(ReplayError::Corrupt(format!(
+                "input on tick {} comes after an input on tick {}",
+                pair[1].tick, pair[0].tick
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Number of ticks covered by the replay
+    pub fn len(&self) -> u64 {
+        self.final_tick.saturating_sub(self.initial.gamestate.ticks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Play the replay from the start, one tick at a time
+    pub fn runner(&self) -> ReplayRunner<'_> {
+        ReplayRunner {
+            replay: self,
+            session: self.initial.clone(),
+            cursor: 0,
+        }
+    }
+
+    /// Re-simulate the whole replay, checking that it ends in the recorded state
+    pub fn verify(&self) -> Result<SessionSnapshot, ReplayError> {
+        self.validate()?;
+
+        let mut runner = self.runner();
+        while !runner.is_finished() {
+            runner.step();
+        }
+
+        let found = runner.session.gamestate.checksum();
+        match found == self.checksum {
+            true => Ok(runner.session),
+            false => Err(ReplayError::Desync {
+                expected: self.checksum,
+                found,
+            }),
+        }
+    }
+}
+
+/// Steps through a replay, applying the recorded inputs as the ticks come up
+#[derive(Debug, Clone)]
+pub struct ReplayRunner<'a> {
+    replay: &'a Replay,
+    session: SessionSnapshot,
+    /// Index of the next input to apply
+    cursor: usize,
+}
+
+impl<'a> ReplayRunner<'a> {
+    /// Continue a replay from a session that was reached by playing it, such as a keyframe
+    pub fn resume(replay: &'a Replay, session: SessionSnapshot) -> Self {
+        let tick = session.gamestate.ticks;
+        let cursor = replay.inputs.partition_point(|input| input.tick < tick);
+
+        Self {
+            replay,
+            session,
+            cursor,
+        }
+    }
+
+    /// The session as of the latest tick
+    pub fn session(&self) -> &SessionSnapshot {
+        &self.session
+    }
+
+    /// Stop playing, keeping the session where it was left
+    pub fn into_session(self) -> SessionSnapshot {
+        self.session
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.session.gamestate.ticks >= self.replay.final_tick
+    }
+
+    /// Apply the inputs for the next tick and run it,
+    /// returning what happened in the ruleset the same way a live session would
+    pub fn step(&mut self) -> Vec<ModeEvent> {
+        let SessionSnapshot { gamestate, mode } = &mut self.session;
+
+        while let Some(recorded) = self.replay.inputs.get(self.cursor) {
+            if recorded.tick > gamestate.ticks {
+                break;
+            }
+
+            // inputs from players that are not in the replay are skipped, see [`Replay::validate`]
+            if let Some(player) = self.replay.players.get(recorded.player as usize) {
+                recorded.input.apply(player, gamestate, mode);
+            }
+            self.cursor += 1;
+        }
+
+        let events = mode.update(gamestate);
+        gamestate.tick();
+        events
+    }
+}
+
+/// Problems found while reading or playing back a replay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The file does not start like a replay file
+    NotAReplay,
+    /// The replay was written in a format that cannot be read
+    UnsupportedVersion(u32),
+    /// The contents of the replay could not be decoded
+    Corrupt(String),
+    /// Playing the replay ended in a different state than the one recorded
+    Desync { expected: u64, found: u64 },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::NotAReplay => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(v) => write!(f, "unsupported replay version {}", v),
+            ReplayError::Corrupt(e) => write!(f, "corrupt replay: {}", e),
+            ReplayError::Desync { expected, found } => write!(
+                f,
+                "replay ended with checksum {:016x} instead of {:016x}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
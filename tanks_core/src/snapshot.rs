@@ -0,0 +1,22 @@
+//! The complete state of a session, which can be saved and later resumed exactly where it left off
+
+use serde::{Deserialize, Serialize};
+
+use crate::{common::gamestate::GameState, modes::GameMode};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct SessionSnapshot {
+    /// Every Tank, bullet and tile along with the tick count and RNG of the simulation
+    pub gamestate: GameState,
+    /// The ruleset along with its progress and timers
+    pub mode: GameMode,
+}
+
+impl SessionSnapshot {
+    pub fn new(gamestate: &GameState, mode: &GameMode) -> Self {
+        Self {
+            gamestate: gamestate.clone(),
+            mode: mode.clone(),
+        }
+    }
+}
@@ -0,0 +1,480 @@
+//! Interface for training agents with reinforcement learning, which runs a [`GameState`]
+//! directly without any server
+//!
+//! Every call to [`TrainingEnv::step`] applies one [`Action`] for each agent, runs the
+//! simulation for a few ticks and returns what every agent can observe along with its reward.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{
+        bot::BotArchetype,
+        constants::{MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH},
+        environment::{Environment, Tile},
+        gamestate::GameState,
+        health::{DamageEvent, HealthConfig},
+        player::Player,
+    },
+    generator::GeneratorConfig,
+    utils::Vector2,
+};
+
+/// How the surroundings of an agent are described to it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObservationEncoding {
+    /// Lists of the Tanks, bullets and walls with their exact positions
+    #[default]
+    Entities,
+    /// The map rasterized into one layer per [`GridChannel`] at the resolution of the tiles
+    Grid,
+}
+
+/// The layers of a [`GridObservation`], in the order they are stored
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridChannel {
+    /// Walls that stop both Tanks and bullets
+    Walls,
+    /// Ground that gets in the way of Tanks but not bullets, which is holes, mud, water and ice
+    Hazards,
+    /// The Tank of the agent that is observing
+    Own,
+    /// Tanks that are friendly to the agent
+    Allies,
+    /// Tanks that are hostile to the agent
+    Hostiles,
+    Bullets,
+}
+
+impl GridChannel {
+    pub const ALL: [GridChannel; 6] = [
+        GridChannel::Walls,
+        GridChannel::Hazards,
+        GridChannel::Own,
+        GridChannel::Allies,
+        GridChannel::Hostiles,
+        GridChannel::Bullets,
+    ];
+}
+
+/// What a single agent controls its Tank with for the duration of a step
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Action {
+    /// Direction to drive in, or zero to stop
+    pub movement: Vector2,
+    /// Angle to point the gun at
+    pub aim: f64,
+    /// Whether to fire at the start of the step
+    pub shoot: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TankObservation {
+    pub position: Vector2,
+    pub velocity: Vector2,
+    pub hull_angle: f64,
+    pub gun_angle: f64,
+    pub alive: bool,
+    /// Whether the Tank is an enemy of the agent that is observing
+    pub hostile: bool,
+    pub hit_points: u32,
+    pub bullets_remaining: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BulletObservation {
+    pub position: Vector2,
+    pub velocity: Vector2,
+    /// Whether the bullet was fired by the agent that is observing
+    pub own: bool,
+    /// Number of times the bullet can still bounce off of walls
+    pub ricochets: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EntityObservation {
+    /// The Tank of the agent that is observing
+    pub own: TankObservation,
+    /// Every other Tank in order of their ids
+    pub tanks: Vec<TankObservation>,
+    pub bullets: Vec<BulletObservation>,
+    /// (row, column) locations of the tiles that block Tanks
+    pub walls: Vec<(usize, usize)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GridObservation {
+    pub width: usize,
+    pub height: usize,
+    /// Values for every channel in the order of [`GridChannel::ALL`], each stored row by row
+    pub data: Vec<f32>,
+}
+
+impl GridObservation {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0.0; GridChannel::ALL.len() * width * height],
+        }
+    }
+
+    fn index(&self, channel: GridChannel, (row, col): (usize, usize)) -> usize {
+        (channel as usize * self.height + row) * self.width + col
+    }
+
+    /// Value of a channel at a (row, column) location
+    pub fn get(&self, channel: GridChannel, location: (usize, usize)) -> f32 {
+        self.data[self.index(channel, location)]
+    }
+
+    fn set(&mut self, channel: GridChannel, location: (usize, usize), value: f32) {
+        if location.0 < self.height && location.1 < self.width {
+            let index = self.index(channel, location);
+            self.data[index] = value;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Observation {
+    Entities(EntityObservation),
+    Grid(GridObservation),
+}
+
+/// What happened to a single agent during a step, which the rewards are decided from
+#[derive(Debug)]
+pub struct StepSummary<'a> {
+    pub agent: &'a str,
+    /// The game after the step
+    pub gamestate: &'a GameState,
+    /// Hits from the projectiles of the agent on other Tanks
+    pub hits_dealt: Vec<&'a DamageEvent>,
+    /// Hits that the Tank of the agent took
+    pub hits_taken: Vec<&'a DamageEvent>,
+    /// Tanks destroyed by the projectiles of the agent
+    pub kills: u32,
+    /// Whether the Tank of the agent was destroyed during the step
+    pub died: bool,
+    /// Whether the episode ended with the agent among the survivors
+    pub won: bool,
+}
+
+/// Reward added on top of the [`RewardWeights`], such as for reaching a part of the map
+pub type RewardHook = Box<dyn FnMut(&StepSummary) -> f64 + Send>;
+
+/// Reward for the outcomes of a step that every task cares about
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RewardWeights {
+    pub kill: f64,
+    pub death: f64,
+    pub win: f64,
+    /// Per point of damage dealt to other Tanks
+    pub damage_dealt: f64,
+    /// Per point of damage taken, usually negative
+    pub damage_taken: f64,
+    /// Given every step, usually a small negative value to hurry the agents along
+    pub step: f64,
+}
+
+impl Default for RewardWeights {
+    fn default() -> Self {
+        Self {
+            kill: 1.0,
+            death: -1.0,
+            win: 1.0,
+            damage_dealt: 0.0,
+            damage_taken: 0.0,
+            step: 0.0,
+        }
+    }
+}
+
+impl RewardWeights {
+    pub fn reward(&self, summary: &StepSummary) -> f64 {
+        let dealt = summary.hits_dealt.iter().map(|hit| hit.damage).sum::<u32>();
+        let taken = summary.hits_taken.iter().map(|hit| hit.damage).sum::<u32>();
+
+        self.kill * summary.kills as f64
+            + self.death * summary.died as u32 as f64
+            + self.win * summary.won as u32 as f64
+            + self.damage_dealt * dealt as f64
+            + self.damage_taken * taken as f64
+            + self.step
+    }
+}
+
+/// Settings for the episodes played by a [`TrainingEnv`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TrainingConfig {
+    /// Ids of the Tanks controlled by the agents, in the order of the actions and observations
+    pub agents: Vec<String>,
+    /// Bots that enter every episode alongside the agents
+    pub bots: Vec<BotArchetype>,
+    /// Maps that each episode picks from, or a map generated from the seed when empty
+    pub maps: Vec<Environment>,
+    pub encoding: ObservationEncoding,
+    pub rewards: RewardWeights,
+    /// Ticks that are run with the same actions for every step
+    pub ticks_per_step: u32,
+    /// Episodes still going after this many ticks are cut short
+    pub max_ticks: u64,
+    /// Whether the agents are a team against the bots, otherwise it is every Tank for itself
+    pub teams: bool,
+    pub ramming: bool,
+    pub health: Option<HealthConfig>,
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        Self {
+            agents: vec![String::from("agent")],
+            bots: vec![BotArchetype::Rookie],
+            maps: Vec::new(),
+            encoding: ObservationEncoding::default(),
+            rewards: RewardWeights::default(),
+            ticks_per_step: 4,
+            max_ticks: 60 * 60 * 2,
+            teams: true,
+            ramming: false,
+            health: None,
+        }
+    }
+}
+
+/// A game that agents play through [`TrainingEnv::reset`] and [`TrainingEnv::step`]
+pub struct TrainingEnv {
+    pub config: TrainingConfig,
+    pub gamestate: GameState,
+    hooks: Vec<RewardHook>,
+    done: bool,
+}
+
+impl TrainingEnv {
+    /// An environment which needs to be [`reset`](Self::reset) before it is stepped
+    pub fn new(config: TrainingConfig) -> Self {
+        Self {
+            config,
+            gamestate: GameState::default(),
+            hooks: Vec::new(),
+            done: true,
+        }
+    }
+
+    /// Shape the rewards with a hook, which is added to the reward of every agent on every step
+    pub fn add_reward_hook(&mut self, hook: impl FnMut(&StepSummary) -> f64 + Send + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Start a new episode, which plays out the same way for the same seed and actions
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        let mut gamestate = GameState::new(seed);
+        gamestate.environment = match self.config.maps.len() {
+            0 => GeneratorConfig::with_seed(seed).generate(),
+            n => self.config.maps[gamestate.rng.below(n)].clone(),
+        };
+        gamestate.teams = self.config.teams;
+        gamestate.ramming = self.config.ramming;
+        gamestate.health = self.config.health.clone();
+
+        for id in &self.config.agents {
+            gamestate.add_player(id.clone());
+        }
+        for (i, archetype) in self.config.bots.iter().enumerate() {
+            let id = format!("bot-{}", i);
+            if let Some(position) = gamestate.spawn_point(&id) {
+                gamestate.spawn_bot(id, *archetype, position);
+            }
+        }
+
+        self.gamestate = gamestate;
+        self.done = false;
+        self.observe()
+    }
+
+    /// Apply an action for every agent and run the ticks of the step,
+    /// returning the observation and reward of every agent along with whether the episode is over
+    pub fn step(&mut self, actions: &[Action]) -> (Vec<Observation>, Vec<f64>, bool) {
+        assert!(!self.done, "the episode is over and needs to be reset");
+        assert_eq!(
+            actions.len(),
+            self.config.agents.len(),
+            "there should be an action for every agent"
+        );
+
+        for (id, action) in self.config.agents.iter().zip(actions) {
+            self.gamestate.set_player_movement(id, &action.movement);
+            self.gamestate.set_player_angle(id, action.aim);
+            if action.shoot {
+                self.gamestate.player_shoot(id);
+            }
+        }
+
+        let alive_before = self.alive_agents();
+        let mut hits = Vec::new();
+        for _ in 0..self.config.ticks_per_step.max(1) {
+            self.gamestate.tick();
+            hits.extend(self.gamestate.damage_events.iter().cloned());
+
+            if self.is_over() {
+                self.done = true;
+                break;
+            }
+        }
+
+        // the agents that are still standing won if there is nobody left for them to fight
+        let cleared = self.done && !self.opponents_remain();
+
+        let mut rewards = Vec::new();
+        for (agent, was_alive) in self.config.agents.iter().zip(alive_before) {
+            let alive = self.gamestate.players[agent].alive;
+            let dealt = hits
+                .iter()
+                .filter(|hit| &hit.source == agent && &hit.target != agent)
+                .collect::<Vec<_>>();
+
+            let summary = StepSummary {
+                agent,
+                gamestate: &self.gamestate,
+                kills: dealt.iter().filter(|hit| hit.hit_points == 0).count() as u32,
+                hits_dealt: dealt,
+                hits_taken: hits.iter().filter(|hit| &hit.target == agent).collect(),
+                died: was_alive && !alive,
+                won: cleared && alive,
+            };
+
+            let shaped = self
+                .hooks
+                .iter_mut()
+                .map(|hook| hook(&summary))
+                .sum::<f64>();
+            rewards.push(self.config.rewards.reward(&summary) + shaped);
+        }
+
+        (self.observe(), rewards, self.done)
+    }
+
+    /// Whether the agents have nobody left to fight, or the episode has run out of time
+    fn is_over(&self) -> bool {
+        !self.opponents_remain() || self.gamestate.ticks >= self.config.max_ticks
+    }
+
+    /// Whether any surviving agent still has a hostile Tank to fight,
+    /// which is never the case once every agent has been destroyed
+    fn opponents_remain(&self) -> bool {
+        let players = &self.gamestate.players;
+
+        self.config
+            .agents
+            .iter()
+            .filter_map(|id| players.get(id))
+            .filter(|agent| agent.alive)
+            .any(|agent| {
+                players
+                    .values()
+                    .any(|other| other.alive && self.gamestate.are_hostile(agent, other))
+            })
+    }
+
+    fn alive_agents(&self) -> Vec<bool> {
+        self.config
+            .agents
+            .iter()
+            .map(|id| self.gamestate.players[id].alive)
+            .collect()
+    }
+
+    /// What every agent can see, in the order of the agents
+    pub fn observe(&self) -> Vec<Observation> {
+        self.config
+            .agents
+            .iter()
+            .map(|id| match self.config.encoding {
+                ObservationEncoding::Entities => Observation::Entities(self.observe_entities(id)),
+                ObservationEncoding::Grid => Observation::Grid(self.observe_grid(id)),
+            })
+            .collect()
+    }
+
+    fn observe_entities(&self, agent: &str) -> EntityObservation {
+        let me = &self.gamestate.players[agent];
+
+        EntityObservation {
+            own: self.observe_tank(me, me),
+            tanks: self
+                .gamestate
+                .players
+                .values()
+                .filter(|p| p.id != agent)
+                .map(|p| self.observe_tank(me, p))
+                .collect(),
+            bullets: self
+                .gamestate
+                .bullets
+                .iter()
+                .map(|bullet| BulletObservation {
+                    position: bullet.position,
+                    velocity: bullet.velocity,
+                    own: bullet.player_id == agent,
+                    ricochets: bullet.ricochets,
+                })
+                .collect(),
+            walls: self
+                .gamestate
+                .environment
+                .tiles
+                .iter()
+                .filter(|(_, tile)| tile.blocks_tanks())
+                .map(|(location, _)| *location)
+                .collect(),
+        }
+    }
+
+    fn observe_tank(&self, me: &Player, tank: &Player) -> TankObservation {
+        TankObservation {
+            position: tank.position,
+            velocity: tank.velocity,
+            hull_angle: tank.hull_angle,
+            gun_angle: tank.gun_angle,
+            alive: tank.alive,
+            hostile: self.gamestate.are_hostile(me, tank),
+            hit_points: tank.hit_points,
+            bullets_remaining: tank.bullets_remaining,
+        }
+    }
+
+    fn observe_grid(&self, agent: &str) -> GridObservation {
+        let me = &self.gamestate.players[agent];
+        let mut grid = GridObservation::new(MAP_BLOCK_WIDTH, MAP_BLOCK_HEIGHT);
+
+        for (location, tile) in &self.gamestate.environment.tiles {
+            match tile {
+                tile if tile.blocks_bullets() => grid.set(GridChannel::Walls, *location, 1.0),
+                Tile::Hole | Tile::Mud | Tile::Water | Tile::Ice => {
+                    grid.set(GridChannel::Hazards, *location, 1.0)
+                }
+                _ => {}
+            }
+        }
+
+        for tank in self.gamestate.players.values().filter(|p| p.alive) {
+            let channel = match tank.id == agent {
+                true => GridChannel::Own,
+                false if self.gamestate.are_hostile(me, tank) => GridChannel::Hostiles,
+                false => GridChannel::Allies,
+            };
+            if let Some(location) = Environment::location_of(&tank.position) {
+                grid.set(channel, location, 1.0);
+            }
+        }
+
+        for bullet in &self.gamestate.bullets {
+            if let Some(location) = Environment::location_of(&bullet.position) {
+                grid.set(GridChannel::Bullets, location, 1.0);
+            }
+        }
+
+        grid
+    }
+}
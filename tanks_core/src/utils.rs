@@ -1,6 +1,8 @@
+use std::hash::Hasher;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
 pub struct Vector2 {
     pub x: f64,
     pub y: f64,
@@ -33,8 +35,123 @@ impl Vector2 {
         Self::new(self.x / mag, self.y / mag)
     }
 
+    pub fn dot(&self, coord: &Vector2) -> f64 {
+        self.x * coord.x + self.y * coord.y
+    }
+
     pub fn rotate(&self, radians: f64) -> Self {
-        Self::new(self.x * radians.cos(), self.y * radians.sin())
+        let (sin, cos) = radians.sin_cos();
+        Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
+/// Rectangle which is rotated about its centre, such as the hull of a Tank
+#[derive(Debug, Clone, Copy)]
+pub struct OrientedBox {
+    pub center: Vector2,
+    /// Half of the width and height of the box before it is rotated
+    pub half_extents: Vector2,
+    /// Rotation of the box in radians
+    pub angle: f64,
+}
+
+impl OrientedBox {
+    pub fn new(center: Vector2, half_extents: Vector2, angle: f64) -> Self {
+        Self {
+            center,
+            half_extents,
+            angle,
+        }
+    }
+
+    /// Box which is not rotated, from its top left corner and size
+    pub fn axis_aligned(corner: &Vector2, w: f64, h: f64) -> Self {
+        let half_extents = Vector2::new(w / 2.0, h / 2.0);
+        Self::new(corner.plus(&half_extents), half_extents, 0.0)
+    }
+
+    /// Unit vectors along the width and height of the box
+    pub fn axes(&self) -> [Vector2; 2] {
+        let (sin, cos) = self.angle.sin_cos();
+        [Vector2::new(cos, sin), Vector2::new(-sin, cos)]
+    }
+
+    /// Half of the width and height of the smallest unrotated box that contains this one
+    pub fn bounds(&self) -> Vector2 {
+        let [u, v] = self.axes();
+        let (hx, hy) = (self.half_extents.x, self.half_extents.y);
+        Vector2::new(
+            hx * u.x.abs() + hy * v.x.abs(),
+            hx * u.y.abs() + hy * v.y.abs(),
+        )
+    }
+
+    /// Half of the length of the box when it is projected onto a unit axis
+    pub fn projected_radius(&self, axis: &Vector2) -> f64 {
+        let [u, v] = self.axes();
+        self.half_extents.x * u.dot(axis).abs() + self.half_extents.y * v.dot(axis).abs()
+    }
+
+    /// Point of the box that is closest to the given point
+    pub fn closest_point(&self, point: &Vector2) -> Vector2 {
+        let [u, v] = self.axes();
+        let local = point.plus(&self.center.scale(-1.0));
+
+        let x = local
+            .dot(&u)
+            .clamp(-self.half_extents.x, self.half_extents.x);
+        let y = local
+            .dot(&v)
+            .clamp(-self.half_extents.y, self.half_extents.y);
+
+        self.center.plus(&u.scale(x)).plus(&v.scale(y))
+    }
+}
+
+/// Same as [`circle_rect_collision`] for a rotated rectangle,
+/// giving the offset from the circle to the closest point of the box when they overlap
+pub fn circle_box_collision(
+    circle: &Vector2,
+    radius: f64,
+    rect: &OrientedBox,
+) -> Result<(), Vector2> {
+    let offset = rect.closest_point(circle).plus(&circle.scale(-1.0));
+
+    if offset.dot(&offset) < radius * radius {
+        Err(offset)
+    } else {
+        Ok(())
+    }
+}
+
+/// Separating axis test between two rotated rectangles
+///
+/// When they overlap, gives the shortest translation that moves `b` out of `a`.
+pub fn box_box_collision(a: &OrientedBox, b: &OrientedBox) -> Result<(), Vector2> {
+    let between = b.center.plus(&a.center.scale(-1.0));
+
+    let mut shortest: Option<(f64, Vector2)> = None;
+    for axis in a.axes().into_iter().chain(b.axes()) {
+        let distance = between.dot(&axis);
+        let overlap = a.projected_radius(&axis) + b.projected_radius(&axis) - distance.abs();
+
+        // any axis with a gap between the boxes separates them
+        if overlap <= 0.0 {
+            return Ok(());
+        }
+
+        if shortest.is_none_or(|(shortest, _)| overlap < shortest) {
+            let direction = match distance < 0.0 {
+                true => axis.scale(-1.0),
+                false => axis,
+            };
+            shortest = Some((overlap, direction));
+        }
+    }
+
+    match shortest {
+        Some((overlap, direction)) => Err(direction.scale(overlap)),
+        None => Ok(()),
     }
 }
 
@@ -74,9 +191,117 @@ pub fn circle_circle_collision(
 ) -> Result<(), Vector2> {
     let dx = p2.x - p1.x;
     let dy = p2.y - p1.y;
-    if dx * dx + dy * dy < r1 * r2 {
+    if dx * dx + dy * dy < (r1 + r2) * (r1 + r2) {
         Err(Vector2::new(dx, dy))
     } else {
         Ok(())
     }
 }
+
+/// Small seedable random number generator (SplitMix64)
+/// so that anything built from randomness can be reproduced from its seed
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in the range [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform value in the range [low, high)
+    pub fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+
+    /// Uniform index in the range [0, n)
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+/// Hasher (FNV-1a) whose output only depends on the values written to it,
+/// unlike the std hashers which may change between Rust versions.
+/// Integers are written as little endian and `usize` as 64 bits, so the
+/// output is also the same between the server and the browser.
+#[derive(Debug, Clone)]
+pub struct StableHasher {
+    hash: u64,
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self {
+            hash: 0xCBF2_9CE4_8422_2325,
+        }
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Serde helpers for maps whose keys are not strings, such as the (row, column) of a tile,
+/// which are written as a list of key and value pairs so that they can be stored as JSON
+pub mod map_as_pairs {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Vec::<(K, V)>::deserialize(deserializer).map(|pairs| pairs.into_iter().collect())
+    }
+}
@@ -0,0 +1,325 @@
+//! Checks over parsed maps which catch mistakes that would make them unplayable
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt,
+};
+
+use crate::common::{
+    constants::{MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH},
+    environment::{Environment, Tile},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The map can be played, but probably not as intended
+    Warning,
+    /// The map should not be played
+    Error,
+}
+
+/// Something placed onto a single tile of a map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    Tile,
+    Spawn,
+    Spawner,
+    Enemy,
+    Pickup,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+    /// Open ground that cannot be driven to from the spawns
+    UnreachableRegion { tiles: usize },
+    /// A spawn that cannot drive to the other spawns
+    UnreachableSpawn,
+    /// A marker placed on a tile that Tanks cannot drive through
+    InsideWall(Marker),
+    /// The map was designed for more players than it has spawns for
+    TooFewSpawns { spawns: usize, players: usize },
+    /// More than one marker placed on the same tile
+    OverlappingMarkers(Vec<Marker>),
+    /// A map name that was defined more than once
+    DuplicateMap,
+    /// A marker outside of the playable area
+    OutOfBounds(Marker),
+    /// A teleporter channel that does not have exactly two teleporters
+    UnpairedTeleporter { channel: usize, count: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// (row, column) of the tile with the problem, if it concerns a single tile
+    pub location: Option<(usize, usize)>,
+    pub kind: IssueKind,
+}
+
+impl ValidationIssue {
+    fn error(location: Option<(usize, usize)>, kind: IssueKind) -> Self {
+        Self {
+            severity: Severity::Error,
+            location,
+            kind,
+        }
+    }
+
+    fn warning(location: Option<(usize, usize)>, kind: IssueKind) -> Self {
+        Self {
+            severity: Severity::Warning,
+            location,
+            kind,
+        }
+    }
+}
+
+/// Check every map in a list, such as one read by
+/// [`parse_environments_list`](crate::assets::parse_environments_list),
+/// returning the issues alongside the name of the map they were found in
+pub fn validate_environments(maps: &[(String, Environment)]) -> Vec<(String, ValidationIssue)> {
+    let mut issues = Vec::new();
+    let mut seen = HashSet::new();
+
+    for (name, environment) in maps {
+        if !seen.insert(name) {
+            issues.push((
+                name.clone(),
+                ValidationIssue::error(None, IssueKind::DuplicateMap),
+            ));
+        }
+
+        issues.extend(
+            validate_environment(environment)
+                .into_iter()
+                .map(|issue| (name.clone(), issue)),
+        );
+    }
+
+    issues
+}
+
+/// Check a single map for problems, ordered from the most to least severe
+pub fn validate_environment(environment: &Environment) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    let markers = markers(environment);
+
+    // the playable area is limited by both the map definition and the game bounds
+    let width = environment.width.min(MAP_BLOCK_WIDTH);
+    let height = environment.height.min(MAP_BLOCK_HEIGHT);
+
+    let mut by_location = BTreeMap::<(usize, usize), Vec<Marker>>::new();
+    for &(location, marker) in &markers {
+        if location.0 >= height || location.1 >= width {
+            issues.push(ValidationIssue::error(
+                Some(location),
+                IssueKind::OutOfBounds(marker),
+            ));
+        }
+        by_location.entry(location).or_default().push(marker);
+    }
+
+    for (&location, markers) in &by_location {
+        if markers.len() > 1 {
+            issues.push(ValidationIssue::error(
+                Some(location),
+                IssueKind::OverlappingMarkers(markers.clone()),
+            ));
+        }
+
+        let blocked = environment
+            .tiles
+            .get(&location)
+            .is_some_and(|tile| tile.blocks_tanks());
+
+        if blocked {
+            for &marker in markers.iter().filter(|&&m| m != Marker::Tile) {
+                issues.push(ValidationIssue::error(
+                    Some(location),
+                    IssueKind::InsideWall(marker),
+                ));
+            }
+        }
+    }
+
+    if let Some(players) = environment.metadata.players {
+        if environment.spawns.len() < players {
+            issues.push(ValidationIssue::error(
+                None,
+                IssueKind::TooFewSpawns {
+                    spawns: environment.spawns.len(),
+                    players,
+                },
+            ));
+        }
+    }
+
+    let mut teleporters = BTreeMap::<usize, Vec<(usize, usize)>>::new();
+    for (location, tile) in &environment.tiles {
+        if let Tile::Teleporter(channel) = tile {
+            teleporters.entry(*channel).or_default().push(*location);
+        }
+    }
+    for (channel, locations) in teleporters {
+        if locations.len() != 2 {
+            issues.push(ValidationIssue::error(
+                locations.iter().min().copied(),
+                IssueKind::UnpairedTeleporter {
+                    channel,
+                    count: locations.len(),
+                },
+            ));
+        }
+    }
+
+    issues.extend(reachability_issues(environment, width, height));
+
+    issues.sort_by_key(|issue| (std::cmp::Reverse(issue.severity), issue.location));
+    issues
+}
+
+/// Every marker in the environment along with its (row, column)
+fn markers(environment: &Environment) -> Vec<((usize, usize), Marker)> {
+    let mut markers = Vec::new();
+
+    markers.extend(environment.tiles.keys().map(|&l| (l, Marker::Tile)));
+    markers.extend(environment.spawns.iter().map(|&l| (l, Marker::Spawn)));
+    markers.extend(environment.spawners.iter().map(|&l| (l, Marker::Spawner)));
+    markers.extend(
+        environment
+            .enemies
+            .iter()
+            .map(|e| (e.location, Marker::Enemy)),
+    );
+    markers.extend(
+        environment
+            .pickups
+            .iter()
+            .map(|p| (p.location, Marker::Pickup)),
+    );
+
+    markers
+}
+
+fn reachability_issues(
+    environment: &Environment,
+    width: usize,
+    height: usize,
+) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    let is_open = |location: &(usize, usize)| {
+        !environment
+            .tiles
+            .get(location)
+            .is_some_and(|tile| tile.blocks_tanks())
+    };
+
+    // the region reachable from the spawns is the one that gets played
+    let mut open = (0..height)
+        .flat_map(|row| (0..width).map(move |col| (row, col)))
+        .filter(is_open)
+        .collect::<BTreeSet<_>>();
+
+    let origin = environment
+        .spawns
+        .iter()
+        .find(|spawn| open.contains(spawn))
+        .or_else(|| open.first())
+        .copied();
+
+    let Some(origin) = origin else {
+        return issues;
+    };
+
+    let bounded = Environment {
+        width,
+        height,
+        ..environment.clone()
+    };
+
+    let reachable = bounded.reachable_from(origin);
+
+    for spawn in environment.spawns.iter().filter(|s| open.contains(s)) {
+        if !reachable.contains(spawn) {
+            issues.push(ValidationIssue::error(
+                Some(*spawn),
+                IssueKind::UnreachableSpawn,
+            ));
+        }
+    }
+
+    open.retain(|location| !reachable.contains(location));
+
+    // report each of the sealed off regions once
+    while let Some(&start) = open.first() {
+        let region = bounded.reachable_from(start);
+        open.retain(|location| !region.contains(location));
+
+        issues.push(ValidationIssue::warning(
+            Some(start),
+            IssueKind::UnreachableRegion {
+                tiles: region.len(),
+            },
+        ));
+    }
+
+    issues
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Marker::Tile => "tile",
+            Marker::Spawn => "spawn point",
+            Marker::Spawner => "bot spawner",
+            Marker::Enemy => "enemy",
+            Marker::Pickup => "pickup",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning")?,
+            Severity::Error => write!(f, "error")?,
+        }
+
+        if let Some((row, col)) = self.location {
+            write!(f, " at row {}, column {}", row, col)?;
+        }
+
+        write!(f, ": ")?;
+
+        match &self.kind {
+            IssueKind::UnreachableRegion { tiles } => {
+                write!(f, "{} tiles cannot be reached from the spawns", tiles)
+            }
+            IssueKind::UnreachableSpawn => {
+                write!(f, "spawn point cannot reach the other spawns")
+            }
+            IssueKind::InsideWall(marker) => write!(f, "{} is inside of a wall", marker),
+            IssueKind::TooFewSpawns { spawns, players } => write!(
+                f,
+                "map is for {} players but only has {} spawn points",
+                players, spawns
+            ),
+            IssueKind::OverlappingMarkers(markers) => {
+                let names = markers.iter().map(Marker::to_string).collect::<Vec<_>>();
+                write!(f, "overlapping definitions of {}", names.join(", "))
+            }
+            IssueKind::DuplicateMap => write!(f, "map is defined more than once"),
+            IssueKind::OutOfBounds(marker) => {
+                write!(f, "{} is outside of the playable area", marker)
+            }
+            IssueKind::UnpairedTeleporter { channel, count } => write!(
+                f,
+                "teleporter channel {} has {} teleporters instead of a pair",
+                channel, count
+            ),
+        }
+    }
+}
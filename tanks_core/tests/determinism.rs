@@ -0,0 +1,183 @@
+use tanks_core::{
+    common::{bot::BotArchetype, gamestate::GameState},
+    generator::GeneratorConfig,
+    modes::{royale::BattleRoyale, GameMode},
+    replay::{PlayerInput, Replay, ReplayError, ReplayRecorder},
+    snapshot::SessionSnapshot,
+    utils::Vector2,
+};
+
+const TICKS: u64 = 1200;
+
+/// A battle royale between scripted players and bots.
+/// The players join in the given order, which should not change how the game plays out.
+fn setup(seed: u64, player_order: &[&str]) -> SessionSnapshot {
+    let mut gamestate = GameState::new(seed);
+    gamestate.environment = GeneratorConfig::with_seed(seed).generate();
+    gamestate.ramming = true;
+
+    for id in player_order {
+        gamestate.add_player(id.to_string());
+    }
+    gamestate.spawn_bot(
+        String::from("bot-gunner"),
+        BotArchetype::Gunner,
+        Vector2::new(3.5, 3.5),
+    );
+    gamestate.spawn_bot(
+        String::from("bot-hunter"),
+        BotArchetype::Hunter,
+        Vector2::new(18.5, 12.5),
+    );
+
+    SessionSnapshot {
+        gamestate,
+        mode: GameMode::BattleRoyale(BattleRoyale::new()),
+    }
+}
+
+/// Inputs of the scripted players before a tick, where charlie leaves halfway through
+fn scripted_inputs(tick: u64) -> Vec<(&'static str, PlayerInput)> {
+    let mut inputs = Vec::new();
+
+    for (i, id) in ["alpha", "bravo", "charlie"].into_iter().enumerate() {
+        let turn = (tick as f64 / 40.0) + i as f64;
+        inputs.push((id, PlayerInput::Move(Vector2::new(turn.cos(), turn.sin()))));
+        inputs.push((id, PlayerInput::Aim(turn * 1.7)));
+        if (tick + i as u64 * 7).is_multiple_of(30) {
+            inputs.push((id, PlayerInput::Shoot));
+        }
+    }
+    if tick == TICKS / 2 {
+        inputs.push(("charlie", PlayerInput::Leave));
+    }
+
+    inputs
+}
+
+/// Play the scripted inputs for a number of ticks, returning the checksum after every tick
+fn run(session: &mut SessionSnapshot, ticks: u64) -> Vec<u64> {
+    run_recorded(session, ticks, None)
+}
+
+fn run_recorded(
+    session: &mut SessionSnapshot,
+    ticks: u64,
+    mut recorder: Option<&mut ReplayRecorder>,
+) -> Vec<u64> {
+    let SessionSnapshot { gamestate, mode } = session;
+    let mut checksums = Vec::new();
+
+    for _ in 0..ticks {
+        for (id, input) in scripted_inputs(gamestate.ticks) {
+            input.apply(id, gamestate, mode);
+            if let Some(recorder) = recorder.as_deref_mut() {
+                recorder.record(gamestate, id, input);
+            }
+        }
+
+        mode.update(gamestate);
+        gamestate.tick();
+        checksums.push(gamestate.checksum());
+    }
+
+    checksums
+}
+
+fn simulate(seed: u64, player_order: &[&str]) -> Vec<u64> {
+    run(&mut setup(seed, player_order), TICKS)
+}
+
+#[test]
+fn identical_inputs_produce_identical_states() {
+    let first = simulate(42, &["alpha", "bravo", "charlie"]);
+    let second = simulate(42, &["charlie", "alpha", "bravo"]);
+
+    for (tick, (a, b)) in first.iter().zip(&second).enumerate() {
+        assert_eq!(a, b, "simulations diverged on tick {}", tick + 1);
+    }
+}
+
+#[test]
+fn different_seeds_produce_different_states() {
+    let first = simulate(42, &["alpha", "bravo", "charlie"]);
+    let second = simulate(43, &["alpha", "bravo", "charlie"]);
+
+    assert_ne!(first.last(), second.last());
+}
+
+#[test]
+fn restored_snapshots_continue_identically() {
+    let mut original = setup(42, &["alpha", "bravo", "charlie"]);
+    run(&mut original, TICKS / 2);
+
+    let saved = serde_json::to_string(&original).unwrap();
+    let mut restored = serde_json::from_str::<SessionSnapshot>(&saved).unwrap();
+    assert_eq!(original.gamestate.checksum(), restored.gamestate.checksum());
+
+    assert_eq!(run(&mut original, TICKS / 2), run(&mut restored, TICKS / 2));
+}
+
+/// A replay of the scripted game, recorded from the middle of it
+fn record(ticks: u64) -> (Replay, SessionSnapshot) {
+    let mut session = setup(42, &["alpha", "bravo", "charlie"]);
+    run(&mut session, TICKS / 4);
+
+    let mut recorder = ReplayRecorder::new(&session.gamestate, &session.mode);
+    run_recorded(&mut session, ticks, Some(&mut recorder));
+
+    (recorder.finish(&session.gamestate), session)
+}
+
+#[test]
+fn replays_play_back_to_the_recorded_state() {
+    let (replay, session) = record(TICKS / 2);
+
+    let decoded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+    assert_eq!(decoded.len(), TICKS / 2);
+
+    let played = decoded.verify().unwrap();
+    assert_eq!(played.gamestate.checksum(), session.gamestate.checksum());
+    assert!(!played.gamestate.players.contains_key("charlie"));
+}
+
+#[test]
+fn tampered_replays_desync() {
+    let (mut replay, _) = record(TICKS / 2);
+
+    // the players sit still instead of playing out the script
+    replay
+        .inputs
+        .retain(|recorded| !matches!(recorded.input, PlayerInput::Move(_)));
+
+    assert!(matches!(replay.verify(), Err(ReplayError::Desync { .. })));
+    assert_eq!(
+        Replay::from_bytes(b"not a replay").unwrap_err(),
+        ReplayError::NotAReplay
+    );
+}
+
+#[test]
+fn corrupt_replays_are_rejected() {
+    let (replay, _) = record(TICKS / 4);
+    let is_corrupt = |replay: &Replay| {
+        let decoded = Replay::from_bytes(&replay.to_bytes());
+        matches!(decoded, Err(ReplayError::Corrupt(_)))
+            && matches!(replay.verify(), Err(ReplayError::Corrupt(_)))
+    };
+
+    let mut unknown_player = replay.clone();
+    unknown_player.inputs[0].player = unknown_player.players.len() as u32;
+    assert!(is_corrupt(&unknown_player));
+
+    let mut ends_before_it_starts = replay.clone();
+    ends_before_it_starts.final_tick = replay.initial.gamestate.ticks - 1;
+    assert!(is_corrupt(&ends_before_it_starts));
+    assert_eq!(ends_before_it_starts.len(), 0);
+
+    let mut out_of_order = replay.clone();
+    out_of_order.inputs.swap(0, replay.inputs.len() - 1);
+    assert!(is_corrupt(&out_of_order));
+
+    assert!(Replay::from_bytes(&replay.to_bytes()).is_ok());
+}
@@ -0,0 +1,93 @@
+use tanks_core::generator::{GeneratorConfig, Symmetry};
+
+const SYMMETRIES: &[Symmetry] = &[
+    Symmetry::None,
+    Symmetry::Horizontal,
+    Symmetry::Vertical,
+    Symmetry::Rotational,
+];
+
+/// Every configuration that the tests run against, across several seeds
+fn configs() -> impl Iterator<Item = GeneratorConfig> {
+    SYMMETRIES.iter().flat_map(|&symmetry| {
+        (0..20).map(move |seed| GeneratorConfig {
+            seed,
+            symmetry,
+            ..Default::default()
+        })
+    })
+}
+
+fn mirror(config: &GeneratorConfig, (row, col): (usize, usize)) -> (usize, usize) {
+    match config.symmetry {
+        Symmetry::None => (row, col),
+        Symmetry::Horizontal => (row, config.width - 1 - col),
+        Symmetry::Vertical => (config.height - 1 - row, col),
+        Symmetry::Rotational => (config.height - 1 - row, config.width - 1 - col),
+    }
+}
+
+#[test]
+fn the_same_seed_generates_the_same_map() {
+    for config in configs() {
+        assert_eq!(config.generate(), config.generate());
+    }
+
+    assert_ne!(
+        GeneratorConfig::with_seed(1).generate(),
+        GeneratorConfig::with_seed(2).generate()
+    );
+}
+
+#[test]
+fn spawns_are_spaced_apart_and_clear_of_walls() {
+    for config in configs() {
+        let environment = config.generate();
+        let spawns = &environment.spawns;
+
+        assert_eq!(spawns.len(), config.spawns);
+        assert_eq!(environment.metadata.players, Some(spawns.len()));
+
+        for (i, a) in spawns.iter().enumerate() {
+            assert!(!environment.tiles.contains_key(a), "{:?} is walled", a);
+
+            for b in &spawns[i + 1..] {
+                let distance = a.0.abs_diff(b.0) + a.1.abs_diff(b.1);
+                assert!(distance >= 4, "{:?} and {:?} are too close", a, b);
+            }
+        }
+    }
+}
+
+#[test]
+fn spawns_and_walls_mirror_under_the_symmetry() {
+    for config in configs() {
+        let environment = config.generate();
+
+        for spawn in &environment.spawns {
+            assert!(environment.spawns.contains(&mirror(&config, *spawn)));
+        }
+        for (location, tile) in &environment.tiles {
+            assert_eq!(
+                environment.tiles.get(&mirror(&config, *location)),
+                Some(tile)
+            );
+        }
+    }
+}
+
+#[test]
+fn crowded_maps_bring_spawns_closer_without_sharing_tiles() {
+    let config = GeneratorConfig {
+        width: 4,
+        height: 4,
+        spawns: 6,
+        ..Default::default()
+    };
+    let spawns = config.generate().spawns;
+
+    assert_eq!(spawns.len(), 6);
+    for (i, spawn) in spawns.iter().enumerate() {
+        assert!(!spawns[i + 1..].contains(spawn));
+    }
+}
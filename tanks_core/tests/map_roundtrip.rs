@@ -0,0 +1,163 @@
+use proptest::prelude::*;
+use tanks_core::{
+    assets::{parse_environments_file, write_environments_file, MapParseError, MapParseErrorKind},
+    common::environment::MapMode,
+};
+
+/// Every symbol of the map format, including each tile variant and elevation
+const SYMBOLS: &[char] = &[
+    '.', ' ', 'x', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'o', 'm', 'w', 'i', 'T', 'U', 'V',
+    'W', 'p', 'A', 'B', 'C', 'D', '*', 'r', 'g', 'h', '+', '>', '$',
+];
+
+const MODES: &[MapMode] = &[
+    MapMode::Versus,
+    MapMode::Campaign,
+    MapMode::Survival,
+    MapMode::BattleRoyale,
+];
+
+/// Rows of random symbols, which are all as wide as each other
+fn grid() -> impl Strategy<Value = Vec<String>> {
+    (1..30usize).prop_flat_map(|width| {
+        prop::collection::vec(
+            prop::collection::vec(prop::sample::select(SYMBOLS), width)
+                .prop_map(|row| row.into_iter().collect::<String>()),
+            1..20,
+        )
+    })
+}
+
+fn map_definition() -> impl Strategy<Value = String> {
+    (
+        "[a-z][a-z0-9-]{0,12}",
+        prop::option::of("[A-Za-z][A-Za-z0-9 ]{0,16}[A-Za-z0-9]"),
+        prop::option::of(0usize..64),
+        prop::option::of(prop::sample::select(MODES)),
+        grid(),
+    )
+        .prop_map(|(name, author, players, mode, rows)| {
+            let mut text = format!("@{}\n", name);
+            if let Some(author) = author {
+                text.push_str(&format!(":author {}\n", author));
+            }
+            if let Some(players) = players {
+                text.push_str(&format!(":players {}\n", players));
+            }
+            if let Some(mode) = mode {
+                text.push_str(&format!(":mode {}\n", mode.name()));
+            }
+            for row in rows {
+                text.push_str(&row);
+                text.push('\n');
+            }
+            text.push_str("@\n");
+            text
+        })
+}
+
+proptest! {
+    #[test]
+    fn parse_write_parse_is_identical(maps in prop::collection::vec(map_definition(), 1..4)) {
+        let file = format!("!version 2\n{}", maps.join("\n"));
+
+        let parsed = parse_environments_file(file.as_bytes()).expect("generated map is valid");
+        let written = write_environments_file(&parsed);
+        let reparsed = parse_environments_file(written.as_bytes()).expect("written map is valid");
+
+        prop_assert_eq!(parsed, reparsed);
+    }
+}
+
+#[test]
+fn existing_map_data_round_trips() {
+    let original = parse_environments_file(include_bytes!("../../assets/mapdata.ed")).unwrap();
+    let written = write_environments_file(&original);
+
+    assert_eq!(
+        parse_environments_file(written.as_bytes()).unwrap(),
+        original
+    );
+}
+
+#[test]
+fn version_one_files_only_read_walls() {
+    let maps = parse_environments_file(b"@legacy\n.1x6\npoT*\n@\n").unwrap();
+    let environment = &maps["legacy"];
+
+    assert_eq!(environment.tiles.len(), 3);
+    assert!(environment.spawns.is_empty());
+    assert!(environment.spawners.is_empty());
+    assert_eq!((environment.width, environment.height), (4, 2));
+}
+
+/// Position and kind of the error reported for a file
+fn parse_error(file: &str) -> (usize, usize, MapParseErrorKind) {
+    let MapParseError { line, column, kind } =
+        parse_environments_file(file.as_bytes()).unwrap_err();
+    (line, column, kind)
+}
+
+#[test]
+fn unknown_symbols_are_reported_where_they_are() {
+    assert_eq!(
+        parse_error("!version 2\n@arena\n....\n.?..\n@\n"),
+        (4, 2, MapParseErrorKind::UnknownSymbol('?'))
+    );
+}
+
+#[test]
+fn bad_headers_are_reported() {
+    assert_eq!(
+        parse_error("!version 9\n@arena\n....\n@\n"),
+        (
+            1,
+            10,
+            MapParseErrorKind::UnsupportedVersion(String::from("9"))
+        )
+    );
+    assert_eq!(
+        parse_error("!version 2\n@arena\n:players many\n....\n@\n"),
+        (
+            3,
+            2,
+            MapParseErrorKind::InvalidMetadata {
+                key: String::from("players"),
+                value: String::from("many"),
+            }
+        )
+    );
+    assert_eq!(
+        parse_error("!version 2\n@\n....\n@\n"),
+        (2, 2, MapParseErrorKind::MissingMapName)
+    );
+}
+
+#[test]
+fn ragged_rows_are_reported_where_they_stop_matching() {
+    let short = parse_error("!version 2\n@arena\n....\n..\n@\n");
+    assert_eq!(
+        short,
+        (
+            4,
+            3,
+            MapParseErrorKind::RaggedRow {
+                expected: 4,
+                found: 2
+            }
+        )
+    );
+
+    let long = parse_error("!version 2\n@arena\n....\n....\n......\n@\n");
+    assert_eq!(
+        long,
+        (
+            5,
+            5,
+            MapParseErrorKind::RaggedRow {
+                expected: 4,
+                found: 6
+            }
+        )
+    );
+}
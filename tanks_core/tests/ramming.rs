@@ -0,0 +1,57 @@
+use tanks_core::{common::gamestate::GameState, utils::Vector2};
+
+/// Two hostile Tanks a short way apart on an open map, with ramming enabled
+fn face_off() -> GameState {
+    let mut gamestate = GameState::new(0);
+    gamestate.ramming = true;
+
+    for (id, x) in [("left", 5.5), ("right", 6.5)] {
+        gamestate.add_player(id.to_string());
+        let player = gamestate.players.get_mut(id).unwrap();
+        player.position = Vector2::new(x, 5.5);
+    }
+
+    gamestate
+}
+
+fn run_until_touching(gamestate: &mut GameState) {
+    for _ in 0..30 {
+        gamestate.tick();
+    }
+}
+
+#[test]
+fn driving_into_a_stationary_tank_only_pushes_it() {
+    let mut gamestate = face_off();
+    gamestate.set_player_movement("left", &Vector2::new(1.0, 0.0));
+
+    run_until_touching(&mut gamestate);
+
+    assert!(gamestate.players["left"].alive);
+    assert!(gamestate.players["right"].alive);
+    assert!(gamestate.players["right"].position.x > 6.5);
+}
+
+#[test]
+fn head_on_collisions_destroy_the_slower_tank() {
+    let mut gamestate = face_off();
+    gamestate.set_player_movement("left", &Vector2::new(1.0, 0.0));
+    // coming in at an angle, so less of its speed goes into the collision
+    gamestate.set_player_movement("right", &Vector2::new(-1.0, 0.5));
+
+    run_until_touching(&mut gamestate);
+
+    assert!(gamestate.players["left"].alive);
+    assert!(!gamestate.players["right"].alive);
+}
+
+#[test]
+fn equally_fast_tanks_destroy_each_other() {
+    let mut gamestate = face_off();
+    gamestate.set_player_movement("left", &Vector2::new(1.0, 0.0));
+    gamestate.set_player_movement("right", &Vector2::new(-1.0, 0.0));
+
+    run_until_touching(&mut gamestate);
+
+    assert!(gamestate.players.values().all(|p| !p.alive));
+}
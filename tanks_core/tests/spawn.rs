@@ -0,0 +1,30 @@
+use tanks_core::common::{
+    constants::{MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH},
+    environment::Tile,
+    gamestate::GameState,
+};
+
+#[test]
+fn tanks_are_not_placed_on_a_map_without_room() {
+    let mut gamestate = GameState::new(0);
+    for row in 0..MAP_BLOCK_HEIGHT {
+        for col in 0..MAP_BLOCK_WIDTH {
+            gamestate
+                .environment
+                .tiles
+                .insert((row, col), Tile::IndestructableWall(1));
+        }
+    }
+
+    assert_eq!(gamestate.spawn_point("player"), None);
+
+    gamestate.add_player(String::from("player"));
+    assert!(!gamestate.players["player"].alive);
+
+    // a tile opening up gives the Tank somewhere to come back at
+    gamestate.environment.tiles.remove(&(3, 4));
+    gamestate.respawn("player");
+    let player = &gamestate.players["player"];
+    assert!(player.alive);
+    assert_eq!((player.position.x, player.position.y), (4.5, 3.5));
+}
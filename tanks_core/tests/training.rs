@@ -0,0 +1,112 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use tanks_core::{
+    common::{bot::BotArchetype, environment::Environment},
+    training::{
+        Action, GridChannel, Observation, ObservationEncoding, TrainingConfig, TrainingEnv,
+    },
+    utils::Vector2,
+};
+
+/// An agent that circles around while firing at whatever is in front of it
+fn scripted_action(step: u32) -> Action {
+    let turn = step as f64 / 10.0;
+    Action {
+        movement: Vector2::new(turn.cos(), turn.sin()),
+        aim: turn * 2.0,
+        shoot: step.is_multiple_of(5),
+    }
+}
+
+fn play(seed: u64, steps: u32) -> (Vec<Observation>, Vec<f64>) {
+    let mut env = TrainingEnv::new(TrainingConfig {
+        bots: vec![BotArchetype::Gunner, BotArchetype::Hunter],
+        ..Default::default()
+    });
+
+    let mut observations = env.reset(seed);
+    let mut rewards = Vec::new();
+    for step in 0..steps {
+        let (observed, rewarded, done) = env.step(&[scripted_action(step)]);
+        observations = observed;
+        rewards.extend(rewarded);
+        if done {
+            break;
+        }
+    }
+
+    (observations, rewards)
+}
+
+#[test]
+fn episodes_with_the_same_seed_play_out_identically() {
+    assert_eq!(play(7, 300), play(7, 300));
+    assert_ne!(play(7, 300).0, play(8, 300).0);
+}
+
+#[test]
+fn grids_place_the_agent_on_its_own_channel() {
+    let mut env = TrainingEnv::new(TrainingConfig {
+        encoding: ObservationEncoding::Grid,
+        ..Default::default()
+    });
+
+    let observations = env.reset(3);
+    let Observation::Grid(grid) = &observations[0] else {
+        panic!("expected a grid observation");
+    };
+
+    assert_eq!(
+        grid.data.len(),
+        GridChannel::ALL.len() * grid.width * grid.height
+    );
+
+    let location = Environment::location_of(&env.gamestate.players["agent"].position).unwrap();
+    assert_eq!(grid.get(GridChannel::Own, location), 1.0);
+    assert_eq!(grid.get(GridChannel::Hostiles, location), 0.0);
+
+    let hostiles = (0..grid.height)
+        .flat_map(|row| (0..grid.width).map(move |col| (row, col)))
+        .filter(|location| grid.get(GridChannel::Hostiles, *location) == 1.0)
+        .count();
+    assert_eq!(hostiles, 1);
+}
+
+#[test]
+fn episodes_end_when_time_runs_out_and_call_the_reward_hooks() {
+    let mut env = TrainingEnv::new(TrainingConfig {
+        // a rookie never moves, so it stays out of reach of an agent that sits still
+        bots: vec![BotArchetype::Rookie],
+        ticks_per_step: 5,
+        max_ticks: 100,
+        ..Default::default()
+    });
+
+    let calls = Arc::new(AtomicU32::new(0));
+    let counter = calls.clone();
+    env.add_reward_hook(move |summary| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        match summary.died {
+            true => 0.0,
+            false => 0.5,
+        }
+    });
+
+    env.reset(11);
+    let mut steps = 0;
+    loop {
+        steps += 1;
+        let (_, rewards, done) = env.step(&[Action::default()]);
+        if done {
+            break;
+        }
+        assert_eq!(rewards, vec![0.5]);
+    }
+
+    assert_eq!(env.gamestate.ticks, 100);
+    assert_eq!(steps, 20);
+    assert_eq!(calls.load(Ordering::Relaxed), 20);
+}
@@ -0,0 +1,147 @@
+use tanks_core::{
+    assets::parse_environments_list,
+    common::environment::{Environment, Pickup, PickupKind},
+    validation::{
+        validate_environment, validate_environments, IssueKind, Marker, Severity, ValidationIssue,
+    },
+};
+
+/// Every map in a version 2 file
+fn parse(file: &str) -> Vec<(String, Environment)> {
+    parse_environments_list(format!("!version 2\n{}", file).as_bytes()).unwrap()
+}
+
+/// A single map with the given rows
+fn map(rows: &str) -> Environment {
+    parse(&format!("@test\n{}@\n", rows)).remove(0).1
+}
+
+fn issue(severity: Severity, location: Option<(usize, usize)>, kind: IssueKind) -> ValidationIssue {
+    ValidationIssue {
+        severity,
+        location,
+        kind,
+    }
+}
+
+#[test]
+fn open_maps_have_no_issues() {
+    assert_eq!(validate_environment(&map("p..p\n.11.\n....\n")), vec![]);
+}
+
+#[test]
+fn sealed_off_ground_is_a_warning() {
+    assert_eq!(
+        validate_environment(&map("p.1.\n..1.\n..1.\n")),
+        vec![issue(
+            Severity::Warning,
+            Some((0, 3)),
+            IssueKind::UnreachableRegion { tiles: 3 }
+        )]
+    );
+}
+
+#[test]
+fn spawns_that_cannot_reach_each_other_are_errors() {
+    let issues = validate_environment(&map("p.1p\n..1.\n"));
+
+    assert_eq!(
+        issues,
+        vec![
+            issue(Severity::Error, Some((0, 3)), IssueKind::UnreachableSpawn),
+            issue(
+                Severity::Warning,
+                Some((0, 3)),
+                IssueKind::UnreachableRegion { tiles: 2 }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn markers_inside_walls_are_errors() {
+    let mut environment = map("p1..\n....\n");
+    environment.spawns.push((0, 1));
+
+    assert!(validate_environment(&environment).contains(&issue(
+        Severity::Error,
+        Some((0, 1)),
+        IssueKind::InsideWall(Marker::Spawn)
+    )));
+}
+
+#[test]
+fn maps_need_a_spawn_for_every_player() {
+    assert_eq!(
+        validate_environment(&map(":players 4\np..p\n")),
+        vec![issue(
+            Severity::Error,
+            None,
+            IssueKind::TooFewSpawns {
+                spawns: 2,
+                players: 4
+            }
+        )]
+    );
+}
+
+#[test]
+fn markers_cannot_share_a_tile() {
+    let mut environment = map("p..p\n");
+    environment.pickups.push(Pickup {
+        location: (0, 0),
+        kind: PickupKind::Ammo,
+    });
+
+    assert_eq!(
+        validate_environment(&environment),
+        vec![issue(
+            Severity::Error,
+            Some((0, 0)),
+            IssueKind::OverlappingMarkers(vec![Marker::Spawn, Marker::Pickup])
+        )]
+    );
+}
+
+#[test]
+fn map_names_are_only_defined_once() {
+    let maps = parse("@arena\np..p\n@\n@arena\np..p\n@\n");
+
+    assert_eq!(
+        validate_environments(&maps),
+        vec![(
+            String::from("arena"),
+            issue(Severity::Error, None, IssueKind::DuplicateMap)
+        )]
+    );
+}
+
+#[test]
+fn markers_past_the_edge_of_the_game_are_errors() {
+    // the game is 22 tiles wide, so the last spawn is off of the edge
+    let issues = validate_environment(&map(&format!("p{}p\n", ".".repeat(22))));
+
+    assert_eq!(
+        issues,
+        vec![issue(
+            Severity::Error,
+            Some((0, 23)),
+            IssueKind::OutOfBounds(Marker::Spawn)
+        )]
+    );
+}
+
+#[test]
+fn teleporters_come_in_pairs() {
+    assert_eq!(
+        validate_environment(&map("pT.U\n.U.p\n")),
+        vec![issue(
+            Severity::Error,
+            Some((0, 1)),
+            IssueKind::UnpairedTeleporter {
+                channel: 0,
+                count: 1
+            }
+        )]
+    );
+}
@@ -2,7 +2,18 @@
 
 use serde::{Deserialize, Serialize};
 
-use tanks_core::utils::Vector2;
+use tanks_core::{
+    common::{
+        bullet::Bullet,
+        environment::{Environment, Tile},
+        gamestate::GameState,
+        health::DamageEvent,
+        player::Player,
+        zone::SafeZone,
+    },
+    modes::ModeEvent,
+    utils::Vector2,
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ServerEvent {
@@ -10,9 +21,21 @@ pub enum ServerEvent {
     GameState {
         bullets: Vec<BulletWrapper>,
         tanks: Vec<TankWrapper>,
+        /// The safe area when playing a battle royale
+        zone: Option<SafeZone>,
     },
     BulletExplode(Vector2),
+    /// A Tank was hit, sent when the session gives Tanks hit points
+    Damage(DamageEvent),
+    /// The full set of tiles for the environment that was loaded into the session
+    MapUpdate {
+        tiles: Vec<((usize, usize), Tile)>,
+    },
+    /// Progress in the ruleset of the session, such as campaign levels
+    Mode(ModeEvent),
     // Session Related Rvents
+    /// The seed that the map of a random session was generated from, so it can be played again
+    MapSeed(u64),
     PlayerDisconnect {
         player: String,
     },
@@ -29,7 +52,12 @@ pub struct TankWrapper {
     pub id: String,
     pub position: Vector2,
     pub movement: Vector2,
+    /// Angle the hull of the Tank is facing
+    pub hull_angle: f64,
     pub angle: f64,
+    pub alive: bool,
+    pub hit_points: u32,
+    pub max_hit_points: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -46,5 +74,168 @@ pub enum ClientEvent {
     /// Join a Session with a Given ID
     JoinSession(String),
     CreateSession,
+    /// Create a new Session playing through the campaign levels
+    StartCampaign,
+    /// Create a new Session fighting endless waves of bots
+    StartSurvival,
+    /// Create a new Session where the last Tank inside of a shrinking arena wins
+    StartBattleRoyale,
+    /// Create a new Session on a generated map, using the seed to recreate a previous one
+    CreateRandomSession {
+        seed: Option<u64>,
+    },
+    /// Create a new Session of versus matches played through the configured playlist of maps
+    StartRotation,
+    /// Create a new Session of versus matches where the players vote on the next map
+    StartMapVote,
+    /// Vote for the map of the next match while a vote is running
+    MapVote(String),
+    /// Create a private Session to try out a map, given as the text of a MapData file
+    TestMap {
+        map: String,
+    },
+    /// Create a private copy of the current Session to experiment in without affecting it
+    CloneSession,
     LeaveSession,
 }
+
+/// Messages sent to programs connected to the bot endpoint, which replace the usual
+/// [`ServerEvent`]s with an observation that has to be answered with a [`BotAction`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum BotServerMessage {
+    /// Sent once the bot has entered its session
+    Welcome {
+        id: String,
+        session: String,
+        /// Ticks the bot has to answer each observation in, which are sent after every tick
+        response_ticks: u64,
+        /// Number of strikes after which the bot is disconnected
+        max_strikes: u32,
+    },
+    Observation(BotObservation),
+    /// A message from the bot was ignored, counting as a strike against it
+    Rejected {
+        reason: String,
+        strikes: u32,
+    },
+    /// The bot broke the rules too often and the connection is being closed
+    Disconnected {
+        reason: String,
+    },
+}
+
+/// Everything a bot can know about the game, sent after every tick
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BotObservation {
+    /// The tick the observation was taken on, which the answering [`BotAction`] has to repeat
+    pub tick: u64,
+    /// The action has to arrive before the game reaches this tick
+    pub deadline: u64,
+    /// The Tank of the bot
+    pub you: BotTank,
+    /// Every other Tank in the session
+    pub tanks: Vec<BotTank>,
+    pub bullets: Vec<BotBullet>,
+    /// Every tile of the map, only sent when it changed since the previous observation
+    pub map: Option<Vec<((usize, usize), Tile)>>,
+    /// The safe area when playing a battle royale
+    pub zone: Option<SafeZone>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BotTank {
+    pub id: String,
+    pub team: usize,
+    /// Whether the Tank is an enemy of the bot
+    pub hostile: bool,
+    pub alive: bool,
+    pub position: Vector2,
+    pub velocity: Vector2,
+    pub hull_angle: f64,
+    pub gun_angle: f64,
+    pub hit_points: u32,
+    pub max_hit_points: u32,
+    pub armor: u32,
+    /// Bullets the Tank can still fire before one of its bullets explodes
+    pub ammo: u8,
+    /// Ticks until the Tank can move and fire again after its last shot
+    pub cooldown: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BotBullet {
+    /// The Tank that fired the bullet
+    pub owner: String,
+    pub position: Vector2,
+    pub velocity: Vector2,
+    /// Number of times the bullet can still bounce off of walls
+    pub ricochets: u8,
+}
+
+/// The answer of a bot to an observation
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BotAction {
+    /// The tick of the observation being answered
+    pub tick: u64,
+    /// Direction to drive in, or zero to stop
+    pub movement: Vector2,
+    /// Angle to point the gun at
+    pub aim: f64,
+    pub shoot: bool,
+}
+
+/// The state that is broadcast to clients after every tick
+pub fn convert_gamestate_to_broadcast(gs: &GameState) -> ServerEvent {
+    ServerEvent::GameState {
+        bullets: gs
+            .bullets
+            .iter()
+            .map(
+                |&Bullet {
+                     angle, position, ..
+                 }| BulletWrapper { angle, position },
+            )
+            .collect(),
+        tanks: gs
+            .players
+            .iter()
+            .map(
+                |(
+                    _,
+                    Player {
+                        id,
+                        gun_angle: angle,
+                        position,
+                        movement,
+                        hull_angle,
+                        alive,
+                        hit_points,
+                        max_hit_points,
+                        ..
+                    },
+                )| TankWrapper {
+                    angle: *angle,
+                    id: id.clone(),
+                    movement: *movement,
+                    hull_angle: *hull_angle,
+                    hit_points: *hit_points,
+                    max_hit_points: *max_hit_points,
+                    position: *position,
+                    alive: *alive,
+                },
+            )
+            .collect(),
+        zone: gs.zone.clone(),
+    }
+}
+
+/// The tiles of an environment, sent whenever clients need to redraw the map
+pub fn convert_environment_to_broadcast(environment: &Environment) -> ServerEvent {
+    ServerEvent::MapUpdate {
+        tiles: environment
+            .tiles
+            .iter()
+            .map(|(loc, tile)| (*loc, tile.clone()))
+            .collect(),
+    }
+}
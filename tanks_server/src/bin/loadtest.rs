@@ -0,0 +1,337 @@
+//! Load generator which plays a running server with many simulated clients at once
+//!
+//! ```sh
+//! cargo run --release --bin loadtest -- [CONFIG.json] [--json FILE]
+//! ```
+//!
+//! The clients are spread across sessions and wander around, aiming and shooting at random.
+//! Once the run is over it prints as JSON how many clients connected and dropped, the time
+//! between the GameStates each client received, which grows once the sessions of the server
+//! fall behind on their ticks, and the time it took for an aim to show up in a GameState broadcast.
+
+use std::{
+    f64::consts::TAU,
+    path::{Path, PathBuf},
+    process::exit,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tanks_client::{Connection, EventSender};
+use tanks_core::utils::{Rng, Vector2};
+use tanks_events::ServerEvent;
+use tokio::time::{sleep, timeout_at, Instant};
+
+/// The server runs 60 GameState ticks every second
+const TICK_MILLIS: f64 = 1000.0 / 60.0;
+/// Aims that have not shown up after this long are given up on, such as when the Tank is gone
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct LoadConfig {
+    /// Address of the server, such as `ws://127.0.0.1:8080`
+    address: String,
+    clients: usize,
+    /// Number of sessions that the clients are divided between
+    sessions: usize,
+    /// Seconds that the clients play for once all of them have connected
+    duration: f64,
+    /// Seconds over which the clients are connected, to avoid a burst of connections
+    ramp_up: f64,
+    /// Milliseconds between the inputs of each client
+    input_interval: u64,
+    seed: u64,
+}
+
+impl Default for LoadConfig {
+    fn default() -> Self {
+        Self {
+            address: String::from("ws://127.0.0.1:8080"),
+            clients: 1000,
+            sessions: 50,
+            duration: 60.0,
+            ramp_up: 10.0,
+            input_interval: 100,
+            seed: 0,
+        }
+    }
+}
+
+/// What a single client went through during the run
+#[derive(Default)]
+struct ClientReport {
+    connected: bool,
+    /// The connection ended before the run was over
+    dropped: bool,
+    events: u64,
+    /// Milliseconds between consecutive GameStates
+    tick_intervals: Vec<f64>,
+    /// Milliseconds from sending an aim until it was part of a GameState
+    broadcast_latencies: Vec<f64>,
+}
+
+/// An aim that was sent and is waiting to show up in a GameState
+type Probe = Arc<Mutex<Option<(f64, Instant)>>>;
+
+#[derive(Serialize)]
+struct Percentiles {
+    samples: usize,
+    p50: f64,
+    p90: f64,
+    p99: f64,
+    max: f64,
+}
+
+impl Percentiles {
+    fn of(mut samples: Vec<f64>) -> Self {
+        samples.sort_by(f64::total_cmp);
+        let at = |fraction: f64| match samples.len() {
+            0 => 0.0,
+            n => samples[((n - 1) as f64 * fraction).round() as usize],
+        };
+
+        Self {
+            samples: samples.len(),
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            max: at(1.0),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct LoadReport {
+    clients: usize,
+    connected: usize,
+    dropped: usize,
+    events_received: u64,
+    /// Share of the GameStates that arrived more than two ticks after the previous one
+    late_tick_ratio: f64,
+    /// Milliseconds between GameStates, which is one tick while the server keeps up
+    tick_interval: Percentiles,
+    /// Milliseconds from sending an input until it was broadcast back
+    broadcast_latency: Percentiles,
+}
+
+fn read_config(path: &Path) -> Result<LoadConfig, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+#[tokio::main]
+async fn main() {
+    let mut config_path = None;
+    let mut json_path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json_path = args.next().map(PathBuf::from),
+            _ => config_path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let config = match &config_path {
+        Some(path) => read_config(path).unwrap_or_else(|e| {
+            eprintln!("failed to read config [{}]: {}", path.display(), e);
+            exit(1);
+        }),
+        None => LoadConfig::default(),
+    };
+    if config.clients == 0 || config.sessions == 0 {
+        eprintln!("at least one client and one session are needed");
+        exit(1);
+    }
+
+    eprintln!(
+        "connecting {} clients to {} sessions on [{}]",
+        config.clients, config.sessions, config.address
+    );
+
+    let config = Arc::new(config);
+    let start = Instant::now();
+    let end = start + Duration::from_secs_f64(config.ramp_up + config.duration);
+    let mut rng = Rng::new(config.seed);
+
+    let handles = (0..config.clients)
+        .map(|index| {
+            let delay = config.ramp_up * index as f64 / config.clients as f64;
+            let connect_at = start + Duration::from_secs_f64(delay);
+            let seed = rng.next_u64();
+            let config = config.clone();
+
+            tokio::spawn(async move {
+                tokio::time::sleep_until(connect_at).await;
+                run_client(&config, index, seed, end).await
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut reports = Vec::with_capacity(handles.len());
+    for handle in handles {
+        reports.push(handle.await.unwrap_or_default());
+    }
+
+    let report = summarize(&config, reports);
+    let json = serde_json::to_string_pretty(&report).expect("the report can be serialized");
+    println!("{}", json);
+
+    if let Some(path) = json_path {
+        if let Err(e) = std::fs::write(&path, &json) {
+            eprintln!("failed to write [{}]: {}", path.display(), e);
+            exit(1);
+        }
+    }
+}
+
+async fn run_client(config: &LoadConfig, index: usize, seed: u64, end: Instant) -> ClientReport {
+    let mut report = ClientReport::default();
+    let name = format!("load-{}", index);
+    let session = format!("load-{}", index % config.sessions);
+
+    let connection = match Connection::connect(&config.address, &name).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("[{}] failed to connect: {}", name, e);
+            return report;
+        }
+    };
+    report.connected = true;
+
+    let (sender, mut events) = connection.split();
+    let probe = Probe::default();
+    let interval = Duration::from_millis(config.input_interval);
+    let driver = tokio::spawn(drive(
+        sender.clone(),
+        session,
+        probe.clone(),
+        seed,
+        interval,
+    ));
+
+    let mut previous_tick = None;
+    loop {
+        let event = match timeout_at(end, events.next()).await {
+            Err(_) => break,
+            Ok(Some(Ok(event))) => event,
+            Ok(Some(Err(e))) => {
+                eprintln!("[{}] dropped: {}", name, e);
+                report.dropped = true;
+                break;
+            }
+            Ok(None) => {
+                eprintln!("[{}] dropped by the server", name);
+                report.dropped = true;
+                break;
+            }
+        };
+        report.events += 1;
+
+        let ServerEvent::GameState { tanks, .. } = event else {
+            continue;
+        };
+
+        let now = Instant::now();
+        if let Some(previous) = previous_tick.replace(now) {
+            let interval: Duration = now - previous;
+            report.tick_intervals.push(interval.as_secs_f64() * 1000.0);
+        }
+
+        let mut pending = probe.lock().unwrap();
+        if let Some((angle, sent)) = *pending {
+            let arrived = tanks
+                .iter()
+                .any(|tank| tank.id == name && (tank.angle - angle).abs() < 1e-9);
+            if arrived {
+                let latency: Duration = now - sent;
+                report
+                    .broadcast_latencies
+                    .push(latency.as_secs_f64() * 1000.0);
+                *pending = None;
+            }
+        }
+    }
+
+    driver.abort();
+    let _ = sender.close().await;
+
+    report
+}
+
+/// Play like a restless player, sending a new aim whenever the previous one has been seen
+async fn drive(sender: EventSender, session: String, probe: Probe, seed: u64, interval: Duration) {
+    let mut rng = Rng::new(seed);
+    if sender.join_session(&session).await.is_err() {
+        return;
+    }
+
+    loop {
+        sleep(interval).await;
+
+        let heading = rng.range(0.0, TAU);
+        let direction = match rng.next_f64() < 0.1 {
+            true => Vector2::new(0.0, 0.0),
+            false => Vector2::new(heading.cos(), heading.sin()),
+        };
+        if sender.move_towards(direction).await.is_err() {
+            return;
+        }
+
+        let aim = {
+            let mut pending = probe.lock().unwrap();
+            match *pending {
+                Some((_, sent)) if sent.elapsed() < PROBE_TIMEOUT => None,
+                _ => {
+                    let angle = rng.range(0.0, TAU);
+                    *pending = Some((angle, Instant::now()));
+                    Some(angle)
+                }
+            }
+        };
+        if let Some(angle) = aim {
+            if sender.aim(angle).await.is_err() {
+                return;
+            }
+        }
+
+        if rng.next_f64() < 0.25 && sender.shoot().await.is_err() {
+            return;
+        }
+    }
+}
+
+fn summarize(config: &LoadConfig, reports: Vec<ClientReport>) -> LoadReport {
+    let mut tick_intervals = Vec::new();
+    let mut broadcast_latencies = Vec::new();
+    let mut report = LoadReport {
+        clients: config.clients,
+        connected: 0,
+        dropped: 0,
+        events_received: 0,
+        late_tick_ratio: 0.0,
+        tick_interval: Percentiles::of(Vec::new()),
+        broadcast_latency: Percentiles::of(Vec::new()),
+    };
+
+    for client in reports {
+        report.connected += client.connected as usize;
+        report.dropped += client.dropped as usize;
+        report.events_received += client.events;
+        tick_intervals.extend(client.tick_intervals);
+        broadcast_latencies.extend(client.broadcast_latencies);
+    }
+
+    let late = tick_intervals
+        .iter()
+        .filter(|interval| **interval > 2.0 * TICK_MILLIS)
+        .count();
+    report.late_tick_ratio = late as f64 / tick_intervals.len().max(1) as f64;
+    report.tick_interval = Percentiles::of(tick_intervals);
+    report.broadcast_latency = Percentiles::of(broadcast_latencies);
+
+    report
+}
@@ -0,0 +1,403 @@
+//! Headless batch simulator which pits bots against each other without any networking
+//!
+//! ```sh
+//! cargo run --release --bin simulate -- [CONFIG.json] [--json FILE] [--csv FILE]
+//! ```
+//!
+//! Every match is a free for all between the bots of the lineup, played until at most one
+//! Tank is left standing. The statistics of all the matches are printed as JSON, and can also
+//! be written to a JSON file or a CSV file with a row for every archetype.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use serde::{Deserialize, Serialize};
+use tanks_core::{
+    assets::parse_environments_list,
+    common::{
+        bot::BotArchetype, constants::BULLET_RICOCHETS, environment::Environment,
+        gamestate::GameState, health::HealthConfig,
+    },
+    generator::GeneratorConfig,
+    utils::{Rng, Vector2},
+};
+
+/// There are 60 GameState ticks every second
+const TICKS_PER_SECOND: f64 = 60.0;
+
+/// What to simulate, read from a JSON file where every field can be left out
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+struct BatchConfig {
+    /// MapData files to load the maps from
+    map_files: Vec<PathBuf>,
+    /// Names of the maps to play on, or every map in the files when empty
+    maps: Vec<String>,
+    /// Number of maps made by the generator to play on as well
+    generated_maps: u32,
+    /// Archetypes of the bots that enter every match
+    lineup: Vec<BotArchetype>,
+    /// Number of matches played on every map
+    matches_per_map: u32,
+    /// Matches still going after this many ticks end in a draw
+    max_ticks: u64,
+    /// Seed that the seeds of every match and generated map are drawn from
+    seed: u64,
+    /// Whether driving into a hostile Tank fast enough destroys it
+    ramming: bool,
+    /// Hit points and armor of the Tanks, otherwise any hit destroys a Tank
+    health: Option<HealthConfig>,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            map_files: vec![PathBuf::from("assets/mapdata.ed")],
+            maps: Vec::new(),
+            generated_maps: 0,
+            lineup: vec![
+                BotArchetype::Rookie,
+                BotArchetype::Gunner,
+                BotArchetype::Hunter,
+            ],
+            matches_per_map: 20,
+            max_ticks: 60 * 60 * 3,
+            seed: 0,
+            ramming: false,
+            health: None,
+        }
+    }
+}
+
+/// A single match to play
+struct MatchSetup<'a> {
+    environment: &'a Environment,
+    seed: u64,
+}
+
+/// How a single match played out
+struct MatchResult {
+    ticks: u64,
+    /// The archetype of every bot that entered
+    entrants: Vec<BotArchetype>,
+    winner: Option<BotArchetype>,
+    /// The archetype that destroyed another Tank, along with the ricochets of the shot
+    kills: Vec<(BotArchetype, u8)>,
+    /// The archetype of every Tank that was destroyed
+    deaths: Vec<BotArchetype>,
+}
+
+#[derive(Serialize, Debug, Default)]
+struct ArchetypeStats {
+    /// Matches that the archetype entered
+    matches: u32,
+    wins: u32,
+    win_rate: f64,
+    kills: u32,
+    deaths: u32,
+    /// Average ticks of the matches that the archetype entered
+    average_ticks: f64,
+    /// Kills made by shots which bounced the given number of times
+    kills_by_ricochets: BTreeMap<u8, u32>,
+    #[serde(skip)]
+    total_ticks: u64,
+}
+
+#[derive(Serialize, Debug, Default)]
+struct BatchStats {
+    matches: u32,
+    draws: u32,
+    average_ticks: f64,
+    average_seconds: f64,
+    archetypes: BTreeMap<String, ArchetypeStats>,
+    /// Kills made by shots which bounced the given number of times
+    kills_by_ricochets: BTreeMap<u8, u32>,
+}
+
+fn main() {
+    let mut config_path = None;
+    let mut json_path = None;
+    let mut csv_path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json_path = args.next().map(PathBuf::from),
+            "--csv" => csv_path = args.next().map(PathBuf::from),
+            _ => config_path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let config = match &config_path {
+        Some(path) => read_config(path).unwrap_or_else(|e| {
+            eprintln!("failed to read config [{}]: {}", path.display(), e);
+            exit(1);
+        }),
+        None => BatchConfig::default(),
+    };
+
+    let maps = load_maps(&config).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1);
+    });
+    if maps.is_empty() || config.lineup.len() < 2 {
+        eprintln!("at least one map and two bots are needed to play a match");
+        exit(1);
+    }
+
+    let mut rng = Rng::new(config.seed);
+    let setups = maps
+        .iter()
+        .flat_map(|(_, environment)| (0..config.matches_per_map).map(move |_| environment))
+        .map(|environment| MatchSetup {
+            environment,
+            seed: rng.next_u64(),
+        })
+        .collect::<Vec<_>>();
+
+    eprintln!("simulating {} matches on {} maps", setups.len(), maps.len());
+    let results = play_matches(&config, &setups);
+    let stats = aggregate(&results);
+
+    let json = serde_json::to_string_pretty(&stats).expect("statistics can be serialized");
+    println!("{}", json);
+
+    let written = json_path
+        .map(|path| (path, json))
+        .into_iter()
+        .chain(csv_path.map(|path| (path, write_csv(&stats))))
+        .try_for_each(|(path, contents)| {
+            std::fs::write(&path, contents)
+                .map_err(|e| format!("failed to write [{}]: {}", path.display(), e))
+        });
+    if let Err(e) = written {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+fn read_config(path: &Path) -> Result<BatchConfig, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&bytes).map_err(|e| e.to_string())
+}
+
+/// The maps named by the config, followed by the generated ones
+fn load_maps(config: &BatchConfig) -> Result<Vec<(String, Environment)>, String> {
+    let mut maps = Vec::new();
+
+    for path in &config.map_files {
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("failed to read maps [{}]: {}", path.display(), e))?;
+        let parsed = parse_environments_list(&bytes)
+            .map_err(|e| format!("failed to parse maps [{}]: {}", path.display(), e))?;
+
+        maps.extend(
+            parsed
+                .into_iter()
+                .filter(|(name, _)| config.maps.is_empty() || config.maps.contains(name)),
+        );
+    }
+
+    let mut rng = Rng::new(config.seed);
+    for _ in 0..config.generated_maps {
+        let seed = rng.next_u64();
+        maps.push((
+            format!("generated-{}", seed),
+            GeneratorConfig::with_seed(seed).generate(),
+        ));
+    }
+
+    Ok(maps)
+}
+
+/// Play the matches on every core of the machine, returning the results in the same order
+fn play_matches(config: &BatchConfig, setups: &[MatchSetup]) -> Vec<MatchResult> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = setups.len().div_ceil(threads).max(1);
+
+    std::thread::scope(|scope| {
+        let handles = setups
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|setup| play_match(config, setup))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("match simulation panicked"))
+            .collect()
+    })
+}
+
+fn play_match(config: &BatchConfig, setup: &MatchSetup) -> MatchResult {
+    let mut gamestate = GameState::new(setup.seed);
+    gamestate.environment = setup.environment.clone();
+    gamestate.ramming = config.ramming;
+    gamestate.health = config.health.clone();
+
+    // the bots themselves play the same way every time, so the matches differ by where they start
+    let mut spawns = gamestate.environment.spawns.clone();
+    for i in (1..spawns.len()).rev() {
+        spawns.swap(i, gamestate.rng.below(i + 1));
+    }
+
+    for (i, archetype) in config.lineup.iter().enumerate() {
+        let id = format!("{:?}-{}", archetype, i).to_lowercase();
+        let position = match spawns.get(i) {
+            Some((row, col)) => Some(Vector2::new(*col as f64 + 0.5, *row as f64 + 0.5)),
+            None => gamestate.spawn_point(&id),
+        };
+        if let Some(position) = position {
+            gamestate.spawn_bot(id, *archetype, position);
+        }
+    }
+
+    let archetype_of =
+        |gamestate: &GameState, id: &str| gamestate.bots.get(id).map(|bot| bot.archetype);
+    let mut kills = Vec::new();
+    let mut deaths = Vec::new();
+
+    while gamestate.ticks < config.max_ticks && gamestate.bots_remaining() > 1 {
+        let alive = gamestate
+            .players
+            .values()
+            .filter(|p| p.alive)
+            .map(|p| p.id.clone())
+            .collect::<Vec<_>>();
+
+        gamestate.tick();
+
+        for hit in gamestate
+            .damage_events
+            .iter()
+            .filter(|hit| hit.hit_points == 0)
+        {
+            if hit.source != hit.target {
+                if let Some(killer) = archetype_of(&gamestate, &hit.source) {
+                    kills.push((killer, hit.ricochets));
+                }
+            }
+        }
+
+        // Tanks can also be destroyed by ramming, which leaves no damage event behind
+        deaths.extend(
+            alive
+                .iter()
+                .filter(|id| !gamestate.players[*id].alive)
+                .filter_map(|id| archetype_of(&gamestate, id)),
+        );
+    }
+
+    let survivors = gamestate
+        .players
+        .values()
+        .filter(|p| p.alive)
+        .filter_map(|p| archetype_of(&gamestate, &p.id))
+        .collect::<Vec<_>>();
+
+    MatchResult {
+        ticks: gamestate.ticks,
+        entrants: config.lineup.clone(),
+        winner: match survivors.as_slice() {
+            [winner] => Some(*winner),
+            _ => None,
+        },
+        kills,
+        deaths,
+    }
+}
+
+fn aggregate(results: &[MatchResult]) -> BatchStats {
+    let mut stats = BatchStats::default();
+    let mut total_ticks = 0;
+
+    for result in results {
+        stats.matches += 1;
+        total_ticks += result.ticks;
+        if result.winner.is_none() {
+            stats.draws += 1;
+        }
+
+        let mut entrants = result.entrants.clone();
+        entrants.sort_by_key(|archetype| format!("{:?}", archetype));
+        entrants.dedup();
+        for archetype in entrants {
+            let archetype_stats = stats
+                .archetypes
+                .entry(format!("{:?}", archetype))
+                .or_default();
+            archetype_stats.matches += 1;
+            archetype_stats.total_ticks += result.ticks;
+            archetype_stats.wins += (result.winner == Some(archetype)) as u32;
+        }
+
+        for (killer, ricochets) in &result.kills {
+            let archetype_stats = stats.archetypes.entry(format!("{:?}", killer)).or_default();
+            archetype_stats.kills += 1;
+            *archetype_stats
+                .kills_by_ricochets
+                .entry(*ricochets)
+                .or_default() += 1;
+            *stats.kills_by_ricochets.entry(*ricochets).or_default() += 1;
+        }
+
+        for victim in &result.deaths {
+            stats
+                .archetypes
+                .entry(format!("{:?}", victim))
+                .or_default()
+                .deaths += 1;
+        }
+    }
+
+    for archetype_stats in stats.archetypes.values_mut() {
+        let matches = archetype_stats.matches.max(1) as f64;
+        archetype_stats.win_rate = archetype_stats.wins as f64 / matches;
+        archetype_stats.average_ticks = archetype_stats.total_ticks as f64 / matches;
+    }
+
+    stats.average_ticks = total_ticks as f64 / stats.matches.max(1) as f64;
+    stats.average_seconds = stats.average_ticks / TICKS_PER_SECOND;
+    stats
+}
+
+/// A row for every archetype, with a column for the kills made by each number of ricochets
+fn write_csv(stats: &BatchStats) -> String {
+    let mut csv = String::from("archetype,matches,wins,win_rate,kills,deaths,average_ticks");
+    for ricochets in 0..=BULLET_RICOCHETS {
+        write!(csv, ",kills_{}_ricochets", ricochets).unwrap();
+    }
+    csv.push('\n');
+
+    for (archetype, archetype_stats) in &stats.archetypes {
+        write!(
+            csv,
+            "{},{},{},{:.4},{},{},{:.1}",
+            archetype,
+            archetype_stats.matches,
+            archetype_stats.wins,
+            archetype_stats.win_rate,
+            archetype_stats.kills,
+            archetype_stats.deaths,
+            archetype_stats.average_ticks,
+        )
+        .unwrap();
+        for ricochets in 0..=BULLET_RICOCHETS {
+            let kills = archetype_stats.kills_by_ricochets.get(&ricochets);
+            write!(csv, ",{}", kills.copied().unwrap_or_default()).unwrap();
+        }
+        csv.push('\n');
+    }
+
+    csv
+}
@@ -0,0 +1,572 @@
+//! Endpoint for programs that play as bots, such as entries of a bot-coding competition
+//!
+//! A bot connects to `/api/bots?id=NAME&session=ID`, joining the session or creating it when
+//! it does not exist. After every tick it is sent a [`BotObservation`], which it answers with
+//! a single [`BotAction`] within [`RESPONSE_TICKS`] ticks. Answering an observation also covers
+//! the ones before it, so a bot that takes a few ticks to think only answers the latest one.
+//! Missed deadlines, stale or extra actions and malformed messages each count as a strike,
+//! answering on time takes a strike away, and reaching [`MAX_STRIKES`] disconnects the bot.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Query, WebSocketUpgrade,
+    },
+    response::IntoResponse,
+    Extension,
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use tanks_core::{
+    common::{
+        environment::Tile,
+        gamestate::GameState,
+        player::{Player, TankState},
+    },
+    replay::PlayerInput,
+    utils::StableHasher,
+};
+use tanks_events::{BotAction, BotBullet, BotObservation, BotServerMessage, BotTank};
+use tokio::sync::{Mutex, Notify};
+use tracing::info;
+
+use crate::{
+    sessions,
+    state::{is_valid_session_id, Client, SharedServerState},
+    ws::{leave_session, new_session},
+    SessionData,
+};
+
+/// Ticks a bot has to answer an observation in
+pub const RESPONSE_TICKS: u64 = 10;
+/// Strikes a bot can collect before it is disconnected
+pub const MAX_STRIKES: u32 = 10;
+
+#[derive(Deserialize)]
+pub struct BotConnectionParams {
+    id: String,
+    session: String,
+}
+
+/// How a connected bot is keeping up with its observations
+#[derive(Debug, Default)]
+pub struct BotLink {
+    /// Ticks of the observations that are waiting to be answered, oldest first
+    unanswered: VecDeque<u64>,
+    strikes: u32,
+    /// Fingerprint of the map in the last observation, so that it is only sent when it changes
+    map_checksum: Option<u64>,
+    /// Whether the bot has been disconnected for breaking the rules
+    kicked: bool,
+    /// Woken once the bot is kicked, which can happen while the bot is not sending anything
+    kick: Arc<Notify>,
+}
+
+impl BotLink {
+    /// Count a strike against the bot, returning the messages that tell it about the strike
+    fn strike(&mut self, reason: String) -> Vec<BotServerMessage> {
+        self.strikes += 1;
+
+        let mut messages = vec![BotServerMessage::Rejected {
+            reason: reason.clone(),
+            strikes: self.strikes,
+        }];
+        if self.strikes >= MAX_STRIKES {
+            self.kicked = true;
+            self.kick.notify_one();
+            messages.push(BotServerMessage::Disconnected { reason });
+        }
+
+        messages
+    }
+
+    /// Answer the observation of a tick along with the ones before it,
+    /// returning why the answer is rejected when that tick is not waiting to be answered
+    fn answer(&mut self, tick: u64) -> Result<(), String> {
+        if !self.unanswered.contains(&tick) {
+            return Err(format!(
+                "tick {} is not waiting to be answered, since it was already answered, \
+                 is past its deadline or was never observed",
+                tick
+            ));
+        }
+
+        self.unanswered.retain(|unanswered| *unanswered > tick);
+        self.strikes = self.strikes.saturating_sub(1);
+        Ok(())
+    }
+}
+
+pub async fn bot_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<BotConnectionParams>,
+    Extension(state): Extension<SharedServerState<SessionData>>,
+) -> impl IntoResponse {
+    if state.clients.lock().await.contains_key(&params.id) {
+        return format!("User [{}] Already Connected", params.id).into_response();
+    }
+    if !is_valid_session_id(&params.session) {
+        return format!("Session ID [{}] Is Not Valid", params.session).into_response();
+    }
+
+    ws.on_upgrade(move |socket| run_bot(state, socket, params))
+}
+
+async fn run_bot(
+    state: SharedServerState<SessionData>,
+    socket: WebSocket,
+    BotConnectionParams { id, session }: BotConnectionParams,
+) {
+    let (sink, mut receiver) = socket.split();
+    let client = Client {
+        id: id.clone(),
+        sender: sessions::spawn_writer(sink),
+        bot: Some(Arc::new(Mutex::new(BotLink::default()))),
+    };
+    let link = client.bot.clone().expect("bot clients have a link");
+
+    let joined = join_session(&state, &client, &session).await;
+    let welcome = match joined {
+        true => BotServerMessage::Welcome {
+            id: id.clone(),
+            session: session.clone(),
+            response_ticks: RESPONSE_TICKS,
+            max_strikes: MAX_STRIKES,
+        },
+        false => BotServerMessage::Disconnected {
+            reason: format!("session [{}] is private", session),
+        },
+    };
+    send_messages(&client, &[welcome]);
+    if !joined {
+        return;
+    }
+
+    info!("bot [{}] joined session [{}]", id, session);
+    state
+        .clients
+        .lock()
+        .await
+        .insert(id.clone(), client.clone());
+
+    let kick = link.lock().await.kick.clone();
+    loop {
+        let message = tokio::select! {
+            message = receiver.next() => message,
+            _ = kick.notified() => break,
+        };
+        let Some(Ok(message)) = message else {
+            break;
+        };
+        let Message::Text(text) = message else {
+            continue;
+        };
+
+        let action = serde_json::from_str::<BotAction>(&text)
+            .map_err(|e| format!("malformed action: {}", e))
+            .and_then(validate_action);
+
+        let mut link = link.lock().await;
+        if link.kicked {
+            break;
+        }
+
+        let rejection = match action.and_then(|action| link.answer(action.tick).map(|_| action)) {
+            Ok(action) => {
+                drop(link);
+
+                apply_action(&state, &id, &session, action).await;
+                continue;
+            }
+            Err(reason) => reason,
+        };
+
+        let messages = link.strike(rejection);
+        let kicked = link.kicked;
+        drop(link);
+
+        send_messages(&client, &messages);
+        if kicked {
+            break;
+        }
+    }
+
+    info!("bot [{}] disconnected", id);
+
+    // the Tank of a kicked bot is taken out of the game, instead of waiting for it to come back
+    if link.lock().await.kicked {
+        if let Some(session) = state.sessions.lock().await.get(&session) {
+            session.data.queue_input(&id, PlayerInput::Leave);
+        }
+    }
+
+    state.clients.lock().await.remove(&id);
+    leave_session(&state, &id, &session).await;
+}
+
+/// Enter the session, creating it if it does not exist, unless it is private
+async fn join_session(
+    state: &SharedServerState<SessionData>,
+    client: &Client,
+    session_id: &str,
+) -> bool {
+    let mut sessions = state.sessions.lock().await;
+
+    match sessions.get_mut(session_id) {
+        Some(session) if session.data.private => false,
+        Some(session) => {
+            session.add_client(client.clone());
+            session.data.queue_input(&client.id, PlayerInput::Join);
+            true
+        }
+        None => {
+            drop(sessions);
+
+            let session = new_session(session_id.to_string(), client.clone()).await;
+            sessions::start(state, session).await;
+            true
+        }
+    }
+}
+
+/// Actions with values that would break the simulation are rejected
+fn validate_action(action: BotAction) -> Result<BotAction, String> {
+    let finite = [action.movement.x, action.movement.y, action.aim]
+        .iter()
+        .all(|value| value.is_finite());
+
+    match finite {
+        true => Ok(action),
+        false => Err(String::from("action contains a value that is not finite")),
+    }
+}
+
+async fn apply_action(
+    state: &SharedServerState<SessionData>,
+    id: &str,
+    session_id: &str,
+    action: BotAction,
+) {
+    let sessions = state.sessions.lock().await;
+    let Some(session) = sessions.get(session_id) else {
+        return;
+    };
+
+    let data = &session.data;
+    data.queue_input(id, PlayerInput::Move(action.movement));
+    data.queue_input(id, PlayerInput::Aim(action.aim));
+    if action.shoot {
+        data.queue_input(id, PlayerInput::Shoot);
+    }
+}
+
+/// What the bots of a session are shown after a tick, taken out of the GameState
+/// so that the session is unlocked again before the observations are handed out
+pub struct BotView {
+    tick: u64,
+    /// Fingerprint of the map, so that it is only sent to bots that have not seen it yet
+    map_checksum: u64,
+    map: Vec<((usize, usize), Tile)>,
+    /// Observation of every bot with a Tank, which is missing the map until it is handed out
+    observations: HashMap<String, BotObservation>,
+}
+
+impl BotView {
+    pub fn new<'a>(gamestate: &GameState, bots: impl Iterator<Item = &'a String>) -> Self {
+        let deadline = gamestate.ticks + RESPONSE_TICKS;
+        let observations = bots
+            .filter_map(|id| gamestate.players.get(id))
+            .map(|me| {
+                let observation = BotObservation {
+                    tick: gamestate.ticks,
+                    deadline,
+                    you: observe_tank(gamestate, me, me),
+                    tanks: gamestate
+                        .players
+                        .values()
+                        .filter(|p| p.id != me.id)
+                        .map(|p| observe_tank(gamestate, me, p))
+                        .collect(),
+                    bullets: gamestate
+                        .bullets
+                        .iter()
+                        .map(|bullet| BotBullet {
+                            owner: bullet.player_id.clone(),
+                            position: bullet.position,
+                            velocity: bullet.velocity,
+                            ricochets: bullet.ricochets,
+                        })
+                        .collect(),
+                    map: None,
+                    zone: gamestate.zone.clone(),
+                };
+                (me.id.clone(), observation)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let map_checksum = {
+            let mut hasher = StableHasher::default();
+            std::hash::Hash::hash(&gamestate.environment.tiles, &mut hasher);
+            std::hash::Hasher::finish(&hasher)
+        };
+        // sessions without bots are spared from copying the map every tick
+        let map = match observations.is_empty() {
+            true => Vec::new(),
+            false => gamestate
+                .environment
+                .tiles
+                .iter()
+                .map(|(location, tile)| (*location, tile.clone()))
+                .collect(),
+        };
+
+        Self {
+            tick: gamestate.ticks,
+            map_checksum,
+            map,
+            observations,
+        }
+    }
+}
+
+/// Messages for a bot after its session has ticked, starting with the deadlines it missed
+pub async fn observe(link: &Mutex<BotLink>, id: &str, view: &mut BotView) -> Vec<BotServerMessage> {
+    let mut link = link.lock().await;
+    if link.kicked {
+        return Vec::new();
+    }
+
+    let Some(mut observation) = view.observations.remove(id) else {
+        return Vec::new();
+    };
+
+    let mut messages = Vec::new();
+    while let Some(&tick) = link.unanswered.front() {
+        if view.tick < tick + RESPONSE_TICKS {
+            break;
+        }
+
+        link.unanswered.pop_front();
+        messages.extend(link.strike(format!("missed the deadline for tick {}", tick)));
+        if link.kicked {
+            return messages;
+        }
+    }
+
+    if link.map_checksum != Some(view.map_checksum) {
+        observation.map = Some(view.map.clone());
+    }
+    link.unanswered.push_back(view.tick);
+    link.map_checksum = Some(view.map_checksum);
+
+    messages.push(BotServerMessage::Observation(observation));
+    messages
+}
+
+fn observe_tank(gamestate: &GameState, me: &Player, tank: &Player) -> BotTank {
+    BotTank {
+        id: tank.id.clone(),
+        team: tank.team,
+        hostile: gamestate.are_hostile(me, tank),
+        alive: tank.alive,
+        position: tank.position,
+        velocity: tank.velocity,
+        hull_angle: tank.hull_angle,
+        gun_angle: tank.gun_angle,
+        hit_points: tank.hit_points,
+        max_hit_points: tank.max_hit_points,
+        armor: tank.armor,
+        ammo: tank.bullets_remaining,
+        cooldown: match tank.state {
+            TankState::Shooting(ticks) => ticks,
+            TankState::Idle => 0,
+        },
+    }
+}
+
+/// Queue messages for a bot, closing the connection after it is told that it was disconnected
+pub fn send_messages(client: &Client, messages: &[BotServerMessage]) {
+    for message in messages {
+        let text = serde_json::to_string(message).unwrap();
+        if let Err(e) = client.sender.try_send(Message::Text(text)) {
+            tracing::error!("transmission error to bot [{}]: [{}]", client.id, e);
+            return;
+        }
+
+        if matches!(message, BotServerMessage::Disconnected { .. }) {
+            info!("bot [{}] kicked", client.id);
+            let _ = client.sender.try_send(Message::Close(None));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What a bot named `bot` is shown after the given tick
+    fn view(tick: u64) -> BotView {
+        let mut gamestate = GameState::new(0);
+        gamestate.add_player(String::from("bot"));
+        gamestate.ticks = tick;
+
+        BotView::new(&gamestate, [String::from("bot")].iter())
+    }
+
+    /// Observe every tick in the range, returning the messages of the last one
+    async fn observe_ticks(
+        link: &Mutex<BotLink>,
+        ticks: std::ops::RangeInclusive<u64>,
+    ) -> Vec<BotServerMessage> {
+        let mut messages = Vec::new();
+        for tick in ticks {
+            messages = observe(link, "bot", &mut view(tick)).await;
+        }
+        messages
+    }
+
+    fn strikes(messages: &[BotServerMessage]) -> Vec<u32> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                BotServerMessage::Rejected { strikes, .. } => Some(*strikes),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn observations_only_carry_the_map_when_it_changes() {
+        let link = Mutex::new(BotLink::default());
+
+        let first = observe(&link, "bot", &mut view(0)).await;
+        let second = observe(&link, "bot", &mut view(1)).await;
+
+        let map = |messages: &[BotServerMessage]| match messages {
+            [BotServerMessage::Observation(observation)] => {
+                assert_eq!(observation.deadline, observation.tick + RESPONSE_TICKS);
+                observation.map.is_some()
+            }
+            _ => panic!("expected a single observation, found {:?}", messages),
+        };
+        assert!(map(&first));
+        assert!(!map(&second));
+        assert_eq!(link.lock().await.unanswered, [0, 1]);
+    }
+
+    #[tokio::test]
+    async fn observations_are_due_within_the_response_ticks() {
+        let link = Mutex::new(BotLink::default());
+
+        // the first observation is still on time right up until its deadline
+        let messages = observe_ticks(&link, 0..=RESPONSE_TICKS - 1).await;
+        assert_eq!(strikes(&messages), Vec::<u32>::new());
+
+        let messages = observe(&link, "bot", &mut view(RESPONSE_TICKS)).await;
+        assert_eq!(strikes(&messages), [1]);
+        assert!(matches!(
+            messages.last(),
+            Some(BotServerMessage::Observation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn every_missed_deadline_is_a_strike() {
+        let link = Mutex::new(BotLink::default());
+        observe_ticks(&link, 0..=2).await;
+
+        let messages = observe(&link, "bot", &mut view(2 + RESPONSE_TICKS)).await;
+
+        assert_eq!(strikes(&messages), [1, 2, 3]);
+        assert_eq!(link.lock().await.strikes, 3);
+    }
+
+    #[tokio::test]
+    async fn answers_cover_the_observations_before_them() {
+        let link = Mutex::new(BotLink::default());
+        observe_ticks(&link, 0..=5).await;
+
+        let mut link = link.lock().await;
+        assert_eq!(link.answer(3), Ok(()));
+        assert_eq!(link.unanswered, [4, 5]);
+
+        // already answered, either directly or by a later answer
+        assert!(link.answer(3).is_err());
+        assert!(link.answer(1).is_err());
+        // never observed
+        assert!(link.answer(6).is_err());
+        assert_eq!(link.unanswered, [4, 5]);
+    }
+
+    #[tokio::test]
+    async fn answered_observations_do_not_miss_their_deadline() {
+        let link = Mutex::new(BotLink::default());
+        observe_ticks(&link, 0..=5).await;
+        link.lock().await.answer(5).unwrap();
+
+        let messages = observe(&link, "bot", &mut view(5 + RESPONSE_TICKS)).await;
+
+        assert_eq!(strikes(&messages), Vec::<u32>::new());
+    }
+
+    #[tokio::test]
+    async fn late_answers_are_rejected() {
+        let link = Mutex::new(BotLink::default());
+        observe_ticks(&link, 0..=RESPONSE_TICKS).await;
+
+        assert!(link.lock().await.answer(0).is_err());
+    }
+
+    #[tokio::test]
+    async fn answering_on_time_takes_a_strike_away() {
+        let link = Mutex::new(BotLink::default());
+        observe_ticks(&link, 0..=RESPONSE_TICKS + 1).await;
+        assert_eq!(link.lock().await.strikes, 2);
+
+        let mut link = link.lock().await;
+        link.answer(RESPONSE_TICKS).unwrap();
+        assert_eq!(link.strikes, 1);
+        link.answer(RESPONSE_TICKS + 1).unwrap();
+        assert_eq!(link.strikes, 0);
+
+        // strikes never go below zero
+        link.unanswered.push_back(RESPONSE_TICKS + 2);
+        link.answer(RESPONSE_TICKS + 2).unwrap();
+        assert_eq!(link.strikes, 0);
+    }
+
+    #[tokio::test]
+    async fn bots_are_kicked_at_the_strike_limit() {
+        let link = Mutex::new(BotLink::default());
+        let kick = link.lock().await.kick.clone();
+
+        for strike in 1..MAX_STRIKES {
+            let messages = link.lock().await.strike(String::from("extra action"));
+            assert_eq!(strikes(&messages), [strike]);
+            assert!(!link.lock().await.kicked);
+        }
+
+        // the last strike is a missed deadline, which is noticed while the bot is silent
+        observe(&link, "bot", &mut view(0)).await;
+        let messages = observe(&link, "bot", &mut view(RESPONSE_TICKS)).await;
+
+        assert_eq!(strikes(&messages), [MAX_STRIKES]);
+        assert!(matches!(
+            messages.last(),
+            Some(BotServerMessage::Disconnected { .. })
+        ));
+        assert!(link.lock().await.kicked);
+        tokio::time::timeout(std::time::Duration::from_secs(1), kick.notified())
+            .await
+            .expect("the bot connection is woken up to be closed");
+
+        // kicked bots are not sent anything else
+        let messages = observe(&link, "bot", &mut view(RESPONSE_TICKS + 1)).await;
+        assert!(messages.is_empty());
+    }
+}
@@ -0,0 +1,26 @@
+//! Optional settings for the server that are read from files when it starts
+
+use tanks_core::common::health::HealthConfig;
+
+lazy_static::lazy_static! {
+    /// Hit points and armor given to the Tanks of new sessions,
+    /// read from the JSON file named by `HEALTH_CONFIG`.
+    /// Without one, any hit destroys a Tank.
+    pub static ref HEALTH: Option<HealthConfig> = read_health_config();
+}
+
+fn read_health_config() -> Option<HealthConfig> {
+    let path = std::env::var("HEALTH_CONFIG").ok()?;
+
+    let config = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string()));
+
+    match config {
+        Ok(config) => Some(config),
+        Err(e) => {
+            tracing::error!("failed to read health config [{}]: {}", path, e);
+            None
+        }
+    }
+}
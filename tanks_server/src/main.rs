@@ -1,34 +1,104 @@
-use std::{env, net::SocketAddr, sync::Arc};
+use std::{env, net::SocketAddr, path::PathBuf, sync::Arc};
 
 use axum::{
-    extract::ws::Message,
     http::StatusCode,
     response::IntoResponse,
     routing::{get, get_service},
     Extension, Router,
 };
-use futures::SinkExt;
-use tanks_core::common::{bullet::Bullet, gamestate::GameState, player::Player};
-use tanks_events::{BulletWrapper, ServerEvent, TankWrapper};
-use tokio::sync::Mutex;
+use tanks_core::{
+    common::gamestate::GameState,
+    modes::GameMode,
+    replay::{PlayerInput, ReplayRecorder},
+};
+use tokio::sync::{mpsc, Mutex};
 use tower_http::services::ServeDir;
 use tracing::{info, Level};
 
-use crate::state::SharedServerState;
+use crate::{sessions::SessionCommand, state::SharedServerState};
 
+mod bots;
+mod config;
+mod maps;
+mod persistence;
+mod replays;
+mod sessions;
 mod state;
 mod tanks;
 mod ws;
 
-#[derive(Default)]
 pub struct SessionContainer {
     gamestate: Arc<Mutex<GameState>>,
+    mode: Arc<Mutex<GameMode>>,
+    /// Whether other clients are kept from joining, such as while testing a map
+    private: bool,
+    /// Inputs of the match being played, when replays are being saved
+    replay: Arc<Mutex<Option<ReplayRecorder>>>,
+    /// Queue of commands for the task that runs the session
+    commands: mpsc::UnboundedSender<SessionCommand>,
+    /// Receiving end of the queue, which is handed to the task once the session is started
+    inbox: std::sync::Mutex<Option<mpsc::UnboundedReceiver<SessionCommand>>>,
+}
+
+impl Default for SessionContainer {
+    fn default() -> Self {
+        let (commands, inbox) = mpsc::unbounded_channel();
+
+        Self {
+            gamestate: Default::default(),
+            mode: Default::default(),
+            private: false,
+            replay: Default::default(),
+            commands,
+            inbox: std::sync::Mutex::new(Some(inbox)),
+        }
+    }
+}
+
+impl SessionContainer {
+    pub fn send(&self, command: SessionCommand) {
+        // the task only stops once the session is gone, which also drops the queue
+        let _ = self.commands.send(command);
+    }
+
+    /// Queue an input from a player, which the session carries out and records for its replay
+    pub fn queue_input(&self, player: &str, input: PlayerInput) {
+        self.send(SessionCommand::Input {
+            player: player.to_string(),
+            input,
+        });
+    }
 }
 
 type SessionData = SessionContainer;
 
 #[tokio::main]
 async fn main() {
+    // `tanks_server verify-replay FILE...` plays back replays instead of starting the server
+    if env::args().nth(1).as_deref() == Some("verify-replay") {
+        let paths = env::args().skip(2).map(PathBuf::from).collect::<Vec<_>>();
+        if replays::verify_replays(&paths) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // `tanks_server validate-maps [DIR]` checks the maps instead of starting the server
+    if env::args().nth(1).as_deref() == Some("validate-maps") {
+        let dir = env::args()
+            .nth(2)
+            .map_or_else(maps::maps_dir, PathBuf::from);
+
+        match maps::validate_maps_dir(&dir) {
+            Ok(false) => return,
+            Ok(true) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("failed to read maps in [{}]: {}", dir.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     let port: u16 = env::var("PORT")
         .unwrap_or_else(|_| String::from("8000"))
         .parse()
@@ -36,53 +106,23 @@ async fn main() {
 
     let state = SharedServerState::<SessionData>::default();
 
-    let state_copy = state.clone();
-    tokio::spawn(async move {
-        loop {
-            for (_, session) in state_copy.sessions.lock().await.iter_mut() {
-                let mut gamestate = session.data.gamestate.lock().await;
-                gamestate.tick();
-                // collect stats to deliver to clients
-
-                let broadcast_data = convert_gamestate_to_broadcast(&gamestate);
-
-                drop(gamestate);
-
-                // broadcast to all clients
-                for client_id in session.active_client_set() {
-                    let state_broadcast =
-                        Message::Text(serde_json::to_string(&broadcast_data).unwrap());
-
-                    if let Err(e) = state_copy
-                        .clients
-                        .lock()
-                        .await
-                        .get_mut(client_id)
-                        .unwrap()
-                        .sender
-                        .lock()
-                        .await
-                        .send(state_broadcast)
-                        .await
-                    {
-                        tracing::error!("transmission error: [{}]", e);
-                    }
-                }
-            }
-
-            tokio::time::sleep(tokio::time::Duration::from_secs_f64(1.0 / 60.0)).await;
-        }
-    });
+    tokio::spawn(maps::watch_maps_dir());
 
     let app = Router::new()
         .route("/api/health", get(health_handler))
         .route("/api/ws", get(ws::websocket_handler))
+        .route("/api/bots", get(bots::bot_handler))
+        .route("/api/maps", get(maps::catalog_handler))
+        .route(
+            "/api/sessions/:id/snapshot",
+            get(persistence::snapshot_handler),
+        )
         .fallback(
             get_service(ServeDir::new("dist")).handle_error(|error| async move {
                 (StatusCode::INTERNAL_SERVER_ERROR, format!("{error}"))
             }),
         )
-        .layer(Extension(state));
+        .layer(Extension(state.clone()));
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
 
@@ -93,10 +133,36 @@ async fn main() {
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
     tracing::debug!("listening on {}", addr);
 
+    // pick up the sessions that were running when the server last stopped
+    if let Some(dir) = persistence::sessions_dir() {
+        let mut restored = Vec::new();
+        for saved in persistence::load_sessions(&dir) {
+            restored.push(saved.id.clone());
+            sessions::start(&state, saved.into_session()).await;
+        }
+
+        tokio::spawn(persistence::expire_restored_sessions(
+            state.clone(),
+            restored,
+        ));
+
+        tokio::spawn(persistence::save_sessions_periodically(state.clone(), dir));
+    }
+
     axum::Server::bind(&addr)
         .serve(app.into_make_service())
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
         .await
         .unwrap();
+
+    // keep the latest state of the sessions for when the server starts again
+    if let Some(dir) = persistence::sessions_dir() {
+        if let Err(e) = persistence::save_sessions(&state, &dir).await {
+            tracing::error!("failed to save sessions to [{}]: {}", dir.display(), e);
+        }
+    }
 }
 
 /// Health Check Endpoint used to verify the service is live
@@ -104,38 +170,3 @@ async fn health_handler() -> impl IntoResponse {
     info!("HEALTH_CHECK ✓");
     "health check ✓".into_response()
 }
-
-fn convert_gamestate_to_broadcast(gs: &GameState) -> ServerEvent {
-    ServerEvent::GameState {
-        bullets: gs
-            .bullets
-            .iter()
-            .map(
-                |&Bullet {
-                     angle, position, ..
-                 }| BulletWrapper { angle, position },
-            )
-            .collect(),
-        tanks: gs
-            .players
-            .iter()
-            .map(
-                |(
-                    _,
-                    Player {
-                        id,
-                        gun_angle: angle,
-                        position,
-                        movement,
-                        ..
-                    },
-                )| TankWrapper {
-                    angle: *angle,
-                    id: id.clone(),
-                    movement: *movement,
-                    position: *position,
-                },
-            )
-            .collect(),
-    }
-}
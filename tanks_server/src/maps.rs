@@ -0,0 +1,323 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, SystemTime},
+};
+
+use axum::{response::IntoResponse, Json};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use tanks_core::{
+    assets::{parse_campaign_file, parse_environments_list},
+    common::environment::{Environment, MapMetadata, MapMode},
+    modes::{
+        campaign::Campaign,
+        rotation::{MapSelection, Rotation},
+        survival::Survival,
+    },
+    validation::{validate_environments, Severity},
+};
+
+/// Directory that maps are loaded from when `MAPS_DIR` is not set,
+/// relative to the directory the server is started from
+pub const DEFAULT_MAPS_DIR: &str = "assets";
+/// Name of the ordered list of campaign levels within the maps directory
+const CAMPAIGN_FILE: &str = "campaign.txt";
+/// Name of the ordered list of maps played by a rotation within the maps directory
+const PLAYLIST_FILE: &str = "playlist.txt";
+/// Number of maps that players choose between in a map vote
+const VOTE_CANDIDATES: usize = 3;
+/// Name of the map that survival waves are played on
+const SURVIVAL_MAP: &str = "survival";
+/// How often the maps directory is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+lazy_static! {
+    /// Every map in the maps directory, which is reloaded whenever the directory changes
+    pub static ref CATALOG: RwLock<MapCatalog> = RwLock::new(MapCatalog::load(&maps_dir()));
+}
+
+/// Read a list of map names from a file in the maps directory
+fn read_map_list(dir: &Path, file: &str) -> Vec<String> {
+    let path = dir.join(file);
+    let names = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| parse_campaign_file(&bytes).map_err(|e| e.to_string()));
+
+    names.unwrap_or_else(|e| {
+        tracing::error!("failed to read map list [{}]: {}", path.display(), e);
+        Vec::new()
+    })
+}
+
+/// The directory containing the MapData files, set using `MAPS_DIR`
+pub fn maps_dir() -> PathBuf {
+    std::env::var("MAPS_DIR")
+        .unwrap_or_else(|_| String::from(DEFAULT_MAPS_DIR))
+        .into()
+}
+
+/// The maps loaded from every MapData file in a directory
+#[derive(Default)]
+pub struct MapCatalog {
+    pub maps: HashMap<String, Environment>,
+    /// The file that each map was loaded from
+    pub files: HashMap<String, PathBuf>,
+    /// Names of the maps played in the campaign, in order
+    pub campaign: Vec<String>,
+    /// Names of the maps played by a rotation, in order
+    pub playlist: Vec<String>,
+    /// Modification times of the files when they were loaded, used to notice changes,
+    /// or [`None`] when the directory could not be read
+    modified: Option<Vec<(PathBuf, Option<SystemTime>)>>,
+}
+
+impl MapCatalog {
+    /// Read every `.ed` file in the directory along with the campaign and playlist.
+    /// Files which cannot be read are skipped, and when a map name is defined in more than
+    /// one file the definition from the file that sorts last is kept.
+    pub fn load(dir: &Path) -> Self {
+        let modified = watched_files(dir)
+            .map_err(|e| {
+                tracing::error!("failed to read maps directory [{}]: {}", dir.display(), e)
+            })
+            .ok();
+
+        let mut catalog = Self {
+            campaign: read_map_list(dir, CAMPAIGN_FILE),
+            playlist: read_map_list(dir, PLAYLIST_FILE),
+            ..Default::default()
+        };
+
+        for (path, _) in modified
+            .iter()
+            .flatten()
+            .filter(|(path, _)| is_map_file(path))
+        {
+            let maps = match std::fs::read(path) {
+                Ok(bytes) => match parse_environments_list(&bytes) {
+                    Ok(maps) => maps,
+                    Err(e) => {
+                        tracing::error!("failed to parse map data [{}]: {}", path.display(), e);
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    tracing::error!("failed to read map data [{}]: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            for (name, environment) in maps {
+                if let Some(other) = catalog.files.insert(name.clone(), path.clone()) {
+                    if &other != path {
+                        tracing::warn!(
+                            "map [{}] from [{}] replaces the one in [{}]",
+                            name,
+                            path.display(),
+                            other.display()
+                        );
+                    }
+                }
+                catalog.maps.insert(name, environment);
+            }
+        }
+
+        tracing::info!(
+            "loaded {} maps from [{}]",
+            catalog.maps.len(),
+            dir.display()
+        );
+        catalog.modified = modified;
+        catalog
+    }
+
+    /// Whether any of the files in the directory were added, removed or changed since loading,
+    /// which includes the directory itself appearing or going missing
+    fn is_stale(&self, dir: &Path) -> bool {
+        watched_files(dir).ok() != self.modified
+    }
+}
+
+fn is_map_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "ed")
+}
+
+/// The files in a directory that the catalog is loaded from in sorted order,
+/// along with when they were last modified
+fn watched_files(dir: &Path) -> std::io::Result<Vec<(PathBuf, Option<SystemTime>)>> {
+    let mut files = std::fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            is_map_file(path)
+                || path
+                    .file_name()
+                    .is_some_and(|name| name == CAMPAIGN_FILE || name == PLAYLIST_FILE)
+        })
+        .map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect::<Vec<_>>();
+
+    files.sort();
+    Ok(files)
+}
+
+/// Keep checking the maps directory and reload the catalog when its files change,
+/// so that edited maps are used by new sessions without restarting the server
+pub async fn watch_maps_dir() {
+    let dir = maps_dir();
+
+    loop {
+        tokio::time::sleep(RELOAD_INTERVAL).await;
+
+        // reading the files blocks, so it is kept off of the threads running the sessions
+        let dir = dir.clone();
+        let reload = tokio::task::spawn_blocking(move || {
+            if CATALOG.read().unwrap().is_stale(&dir) {
+                let catalog = MapCatalog::load(&dir);
+                *CATALOG.write().unwrap() = catalog;
+            }
+        });
+
+        if let Err(e) = reload.await {
+            tracing::error!("failed to reload maps: {}", e);
+        }
+    }
+}
+
+/// A map listed by the catalog endpoint
+#[derive(Serialize)]
+pub struct MapSummary {
+    name: String,
+    file: String,
+    width: usize,
+    height: usize,
+    spawns: usize,
+    metadata: MapMetadata,
+}
+
+/// Catalog Endpoint listing every map that sessions can be played on
+pub async fn catalog_handler() -> impl IntoResponse {
+    let catalog = CATALOG.read().unwrap();
+
+    let mut maps = catalog
+        .maps
+        .iter()
+        .map(|(name, environment)| MapSummary {
+            name: name.clone(),
+            file: catalog
+                .files
+                .get(name)
+                .and_then(|path| path.file_name())
+                .map(|file| file.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            width: environment.width,
+            height: environment.height,
+            spawns: environment.spawns.len(),
+            metadata: environment.metadata.clone(),
+        })
+        .collect::<Vec<_>>();
+    maps.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Json(maps)
+}
+
+/// Create a fresh run through the campaign levels
+pub fn new_campaign() -> Result<Campaign, String> {
+    let catalog = CATALOG.read().unwrap();
+    Campaign::from_maps(&catalog.campaign, &catalog.maps)
+}
+
+/// Create a fresh rotation of versus matches,
+/// either through the playlist or voting between the maps made for versus play
+pub fn new_rotation(vote: bool) -> Rotation {
+    let catalog = CATALOG.read().unwrap();
+    let maps = catalog
+        .maps
+        .iter()
+        .filter(|(_, environment)| {
+            environment.enemies.is_empty()
+                && matches!(
+                    environment.metadata.mode,
+                    None | Some(MapMode::Versus) | Some(MapMode::BattleRoyale)
+                )
+        })
+        .map(|(name, environment)| (name.clone(), environment.clone()))
+        .collect();
+
+    let selection = match vote {
+        true => MapSelection::Vote {
+            candidates: VOTE_CANDIDATES,
+        },
+        false => MapSelection::Playlist(catalog.playlist.clone()),
+    };
+
+    Rotation::new(maps, selection)
+}
+
+/// Create a fresh set of survival waves, or the name of the survival map when it is missing
+pub fn new_survival() -> Result<Survival, String> {
+    let catalog = CATALOG.read().unwrap();
+    let environment = catalog
+        .maps
+        .get(SURVIVAL_MAP)
+        .ok_or_else(|| SURVIVAL_MAP.to_string())?;
+
+    Ok(Survival::new(environment.clone()))
+}
+
+/// Check every MapData file in the directory, printing the issues found in each.
+///
+/// Returns whether any map had an error, including maps which could not be parsed
+/// and map names which are defined in more than one file.
+pub fn validate_maps_dir(dir: &Path) -> std::io::Result<bool> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "ed"));
+    paths.sort();
+
+    let mut failed = false;
+    let mut defined_in = HashMap::<String, &Path>::new();
+
+    for path in &paths {
+        let maps = match parse_environments_list(&std::fs::read(path)?) {
+            Ok(maps) => maps,
+            Err(e) => {
+                println!("{}: error: {}", path.display(), e);
+                failed = true;
+                continue;
+            }
+        };
+
+        for (name, issue) in validate_environments(&maps) {
+            println!("{} [{}] {}", path.display(), name, issue);
+            failed |= issue.severity == Severity::Error;
+        }
+
+        for (name, _) in &maps {
+            match defined_in.get(name) {
+                Some(other) if *other != path.as_path() => {
+                    println!(
+                        "{} [{}] error: map is also defined in {}",
+                        path.display(),
+                        name,
+                        other.display()
+                    );
+                    failed = true;
+                }
+                _ => {
+                    defined_in.insert(name.clone(), path);
+                }
+            }
+        }
+
+        println!("{}: checked {} maps", path.display(), maps.len());
+    }
+
+    Ok(failed)
+}
@@ -0,0 +1,220 @@
+//! Saving sessions to disk so that they survive a restart of the server,
+//! and dumping single sessions so that a bug can be played back from the state it happened in
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{extract::Path as UrlPath, http::StatusCode, response::IntoResponse, Extension, Json};
+use serde::{Deserialize, Serialize};
+use tanks_core::{common::gamestate::GameState, modes::GameMode, snapshot::SessionSnapshot};
+use tokio::sync::Mutex;
+
+use crate::{
+    state::{is_valid_session_id, Session, SharedServerState},
+    ws, SessionContainer, SessionData,
+};
+
+/// How often the sessions are written to disk
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+/// How long a restored session waits for its players to come back before it is removed
+const RESTORE_GRACE_PERIOD: Duration = Duration::from_secs(120);
+
+/// A session as it is written to disk, which is also the format of a session dump
+#[derive(Serialize, Deserialize)]
+pub struct SavedSession {
+    pub id: String,
+    pub private: bool,
+    pub snapshot: SessionSnapshot,
+}
+
+/// The parts of a session that are saved, taken out of the sessions so that they can be
+/// unlocked while waiting on the GameState of each session
+pub struct SessionHandle {
+    id: String,
+    private: bool,
+    gamestate: Arc<Mutex<GameState>>,
+    mode: Arc<Mutex<GameMode>>,
+}
+
+impl SessionHandle {
+    pub fn new(session: &Session<SessionData>) -> Self {
+        Self {
+            id: session.id.clone(),
+            private: session.data.private,
+            gamestate: session.data.gamestate.clone(),
+            mode: session.data.mode.clone(),
+        }
+    }
+}
+
+impl SavedSession {
+    pub async fn from_handle(handle: &SessionHandle) -> Self {
+        let gamestate = handle.gamestate.lock().await;
+        let mode = handle.mode.lock().await;
+
+        Self {
+            id: handle.id.clone(),
+            private: handle.private,
+            snapshot: SessionSnapshot::new(&gamestate, &mode),
+        }
+    }
+
+    /// A session without any clients that resumes from the saved state
+    pub fn into_session(self) -> Session<SessionData> {
+        let mut session = Session::<SessionData>::new(self.id);
+        session.data = SessionContainer {
+            gamestate: Arc::new(Mutex::new(self.snapshot.gamestate)),
+            mode: Arc::new(Mutex::new(self.snapshot.mode)),
+            private: self.private,
+            ..Default::default()
+        };
+        session
+    }
+}
+
+/// The directory that sessions are saved to, set using `SESSIONS_DIR`.
+/// Sessions are only saved when it is set.
+pub fn sessions_dir() -> Option<PathBuf> {
+    std::env::var("SESSIONS_DIR").ok().map(PathBuf::from)
+}
+
+/// Every session saved in the directory, skipping the files that cannot be read
+pub fn load_sessions(dir: &Path) -> Vec<SavedSession> {
+    let mut sessions = Vec::new();
+
+    for path in session_files(dir) {
+        let saved = std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| {
+                serde_json::from_slice::<SavedSession>(&bytes).map_err(|e| e.to_string())
+            });
+
+        match saved {
+            Ok(saved) if is_valid_session_id(&saved.id) => sessions.push(saved),
+            Ok(saved) => tracing::error!(
+                "failed to restore session [{}]: invalid ID [{}]",
+                path.display(),
+                saved.id
+            ),
+            Err(e) => tracing::error!("failed to restore session [{}]: {}", path.display(), e),
+        }
+    }
+
+    tracing::info!(
+        "restored {} sessions from [{}]",
+        sessions.len(),
+        dir.display()
+    );
+    sessions
+}
+
+/// Write every session into the directory, removing the files of sessions that have ended
+pub async fn save_sessions(state: &SharedServerState<SessionData>, dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+
+    let handles = state
+        .sessions
+        .lock()
+        .await
+        .values()
+        .map(SessionHandle::new)
+        .collect::<Vec<_>>();
+
+    let mut saved = Vec::new();
+    for handle in &handles {
+        saved.push(SavedSession::from_handle(handle).await);
+    }
+
+    let mut kept = Vec::new();
+    for session in saved {
+        // the ID names the file, so it must not be able to point outside of the directory
+        if !is_valid_session_id(&session.id) {
+            tracing::error!("not saving session with invalid ID [{}]", session.id);
+            continue;
+        }
+
+        let path = dir.join(format!("{}.json", session.id));
+        // a session that fails to save keeps its previous file
+        kept.push(path.clone());
+
+        // write to the side first so that a crash never leaves behind half of a session
+        let partial = path.with_extension("json.partial");
+        let written = serde_json::to_vec(&session)
+            .map_err(io::Error::from)
+            .and_then(|json| std::fs::write(&partial, json))
+            .and_then(|_| std::fs::rename(&partial, &path));
+
+        if let Err(e) = written {
+            tracing::error!("failed to save session [{}]: {}", path.display(), e);
+        }
+    }
+
+    for path in session_files(dir) {
+        if !kept.contains(&path) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                tracing::error!("failed to remove ended session [{}]: {}", path.display(), e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Keep the saved sessions up to date while the server is running
+pub async fn save_sessions_periodically(state: SharedServerState<SessionData>, dir: PathBuf) {
+    loop {
+        tokio::time::sleep(SAVE_INTERVAL).await;
+
+        if let Err(e) = save_sessions(&state, &dir).await {
+            tracing::error!("failed to save sessions to [{}]: {}", dir.display(), e);
+        }
+    }
+}
+
+/// Remove the restored sessions that nobody has joined once the grace period is over,
+/// since sessions are otherwise only removed when their last client leaves
+pub async fn expire_restored_sessions(state: SharedServerState<SessionData>, ids: Vec<String>) {
+    tokio::time::sleep(RESTORE_GRACE_PERIOD).await;
+
+    for id in ids {
+        ws::remove_if_empty(&state, &id).await;
+    }
+}
+
+/// Dump of a single session, which can be placed into `SESSIONS_DIR` to load it into a server
+pub async fn snapshot_handler(
+    UrlPath(session_id): UrlPath<String>,
+    Extension(state): Extension<SharedServerState<SessionData>>,
+) -> impl IntoResponse {
+    let handle = state
+        .sessions
+        .lock()
+        .await
+        .get(&session_id)
+        .map(SessionHandle::new);
+
+    match handle {
+        Some(handle) => Json(SavedSession::from_handle(&handle).await).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("session [{}] does not exist", session_id),
+        )
+            .into_response(),
+    }
+}
+
+/// The `.json` files in a directory
+fn session_files(dir: &Path) -> Vec<PathBuf> {
+    match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
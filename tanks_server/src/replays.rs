@@ -0,0 +1,118 @@
+//! Recording sessions into replay files which can be played back with `tanks_core`
+
+use std::path::{Path, PathBuf};
+
+use tanks_core::{
+    modes::ModeEvent,
+    replay::{Replay, ReplayError},
+};
+
+use crate::state::is_valid_session_id;
+
+/// The directory that replays are written to, set using `REPLAYS_DIR`.
+/// Sessions are only recorded when it is set.
+pub fn replays_dir() -> Option<PathBuf> {
+    std::env::var("REPLAYS_DIR").ok().map(PathBuf::from)
+}
+
+/// Whether the event means the match being recorded is over
+pub fn ends_match(event: &ModeEvent) -> bool {
+    matches!(
+        event,
+        ModeEvent::MatchEnded { .. }
+            | ModeEvent::RoyaleWinner { .. }
+            | ModeEvent::CampaignComplete
+            | ModeEvent::CampaignFailed { .. }
+            | ModeEvent::SurvivalOver { .. }
+    )
+}
+
+/// Save a finished recording of a session, skipping recordings where nothing happened
+pub fn write_replay(session_id: &str, replay: &Replay) {
+    let Some(dir) = replays_dir() else {
+        return;
+    };
+
+    if replay.is_empty() {
+        return;
+    }
+
+    let Some(path) = replay_path(&dir, session_id, replay.initial.gamestate.ticks) else {
+        return tracing::error!(
+            "not saving replay of session [{}], its ID is not safe to use in a file name",
+            session_id
+        );
+    };
+
+    let written =
+        std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, replay.to_bytes()));
+    match written {
+        Ok(()) => tracing::info!(
+            "replay of session [{}] saved to [{}]",
+            session_id,
+            path.display()
+        ),
+        Err(e) => tracing::error!("failed to save replay [{}]: {}", path.display(), e),
+    }
+}
+
+/// Where the replay of a session starting on `tick` is saved,
+/// or nothing when the session ID could lead outside of `dir`
+fn replay_path(dir: &Path, session_id: &str, tick: u64) -> Option<PathBuf> {
+    is_valid_session_id(session_id).then(|| dir.join(format!("{}-{}.replay", session_id, tick)))
+}
+
+/// Play back every replay file, printing whether each one ends in the recorded state.
+/// Returns whether any of them failed.
+pub fn verify_replays(paths: &[PathBuf]) -> bool {
+    let mut failed = false;
+
+    for path in paths {
+        match verify_replay(path) {
+            Ok(replay) => println!(
+                "{}: ok, {} ticks, {} inputs, checksum {:016x}",
+                path.display(),
+                replay.len(),
+                replay.inputs.len(),
+                replay.checksum
+            ),
+            Err(e) => {
+                failed = true;
+                println!("{}: {}", path.display(), e);
+            }
+        }
+    }
+
+    failed
+}
+
+fn verify_replay(path: &Path) -> Result<Replay, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let replay = Replay::from_bytes(&bytes).map_err(|e| e.to_string())?;
+    replay.verify().map_err(|e: ReplayError| e.to_string())?;
+    Ok(replay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_are_saved_inside_the_replays_dir() {
+        let dir = Path::new("replays");
+
+        assert_eq!(
+            replay_path(dir, "abc-123_X", 42),
+            Some(PathBuf::from("replays/abc-123_X-42.replay"))
+        );
+    }
+
+    #[test]
+    fn hostile_session_ids_are_not_saved() {
+        let dir = Path::new("replays");
+
+        for id in ["../../etc/foo", "/etc/passwd", "a/b", "a\\b", "..", ".", ""] {
+            assert_eq!(replay_path(dir, id, 42), None, "{:?}", id);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Every session runs in its own task on its own timer, so that a busy session or a slow client
+//! only holds up the players of that session
+//!
+//! Client handlers talk to a session by queueing [`SessionCommand`]s, and the session puts its
+//! broadcasts into the outbox of every client, which a separate task writes to the websocket.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::extract::ws::{Message, WebSocket};
+use futures::{stream::SplitSink, SinkExt};
+use tanks_core::{
+    common::gamestate::GameState,
+    modes::{GameMode, ModeEvent},
+    replay::{PlayerInput, ReplayRecorder},
+};
+use tanks_events::{
+    convert_environment_to_broadcast, convert_gamestate_to_broadcast, BotServerMessage, ServerEvent,
+};
+use tokio::sync::{
+    mpsc::{
+        self,
+        error::{TryRecvError, TrySendError},
+    },
+    Mutex,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    bots, replays,
+    state::{Client, Session, SharedServerState},
+    SessionData,
+};
+
+/// Length of a single GameState tick
+const TICK_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Most ticks that are run at once to catch up after the session falls behind,
+/// past which the lost time is dropped instead of speeding up the game
+const MAX_CATCH_UP_TICKS: u32 = 5;
+/// Messages that can be waiting to be written to a client, past which its broadcasts are dropped
+const OUTBOX_CAPACITY: usize = 256;
+
+/// Requests from the client handlers to the task running a session
+#[derive(Debug)]
+pub enum SessionCommand {
+    /// An input of a player, carried out at the start of the next tick
+    Input { player: String, input: PlayerInput },
+    /// Start sending the broadcasts of the session to a client
+    Subscribe(Client),
+    /// Stop sending the broadcasts of the session to a client
+    Unsubscribe(String),
+}
+
+impl Session<SessionData> {
+    /// Add a client to the session, which is sent its broadcasts from then on
+    pub fn add_client(&mut self, client: Client) {
+        self.client_statuses.insert(client.id.clone(), true);
+        self.data.send(SessionCommand::Subscribe(client));
+    }
+
+    pub fn remove_client(&mut self, client_id: &str) {
+        self.set_client_status(client_id, false);
+        self.data
+            .send(SessionCommand::Unsubscribe(client_id.to_string()));
+    }
+}
+
+/// Add the session to the server and start ticking it, replacing any session with the same ID
+///
+/// The task of a session ends once the session has been removed from the server.
+pub async fn start(state: &SharedServerState<SessionData>, session: Session<SessionData>) {
+    let commands = session.data.inbox.lock().unwrap().take();
+
+    if let Some(commands) = commands {
+        let runner = SessionRunner {
+            id: session.id.clone(),
+            gamestate: session.data.gamestate.clone(),
+            mode: session.data.mode.clone(),
+            replay: session.data.replay.clone(),
+            commands,
+            members: HashMap::new(),
+            recording: replays::replays_dir().is_some(),
+        };
+        tokio::spawn(runner.run());
+    }
+
+    state
+        .sessions
+        .lock()
+        .await
+        .insert(session.id.clone(), session);
+}
+
+/// Outbox of a client, whose messages are written to the websocket until the client is gone
+pub fn spawn_writer(mut sink: SplitSink<WebSocket, Message>) -> mpsc::Sender<Message> {
+    let (sender, mut outbox) = mpsc::channel(OUTBOX_CAPACITY);
+
+    tokio::spawn(async move {
+        while let Some(message) = outbox.recv().await {
+            if let Err(e) = sink.send(message).await {
+                return error!("transmission error: [{}]", e);
+            }
+        }
+
+        let _ = sink.close().await;
+    });
+
+    sender
+}
+
+struct SessionRunner {
+    id: String,
+    // shared with the client handlers, but not the queue of commands
+    // since the task is told that the session was removed by the queue closing
+    gamestate: Arc<Mutex<GameState>>,
+    mode: Arc<Mutex<GameMode>>,
+    replay: Arc<Mutex<Option<ReplayRecorder>>>,
+    commands: mpsc::UnboundedReceiver<SessionCommand>,
+    /// Clients that are sent the broadcasts of the session
+    members: HashMap<String, Client>,
+    recording: bool,
+}
+
+/// Ticks to run after some time has passed, see [`due_ticks`]
+#[derive(Debug, PartialEq, Eq)]
+struct DueTicks {
+    ticks: u32,
+    /// Time carried over towards the next tick
+    remainder: Duration,
+    /// Time that was given up on after running [`MAX_CATCH_UP_TICKS`]
+    dropped: Option<Duration>,
+}
+
+/// Count the whole ticks in the time left over from before plus the time that has passed,
+/// running at most [`MAX_CATCH_UP_TICKS`] and dropping whatever is still owed past that
+fn due_ticks(accumulator: Duration, elapsed: Duration) -> DueTicks {
+    let mut remainder = accumulator + elapsed;
+
+    let mut ticks = 0;
+    while remainder >= TICK_DURATION && ticks < MAX_CATCH_UP_TICKS {
+        remainder -= TICK_DURATION;
+        ticks += 1;
+    }
+
+    let dropped = (remainder >= TICK_DURATION).then(|| std::mem::take(&mut remainder));
+    DueTicks {
+        ticks,
+        remainder,
+        dropped,
+    }
+}
+
+impl SessionRunner {
+    /// Advance the session at a fixed rate of ticks, however long each tick takes to run
+    async fn run(mut self) {
+        let mut previous = Instant::now();
+        let mut accumulator = Duration::ZERO;
+
+        loop {
+            let now = Instant::now();
+            let DueTicks {
+                ticks,
+                remainder,
+                dropped,
+            } = due_ticks(accumulator, now - previous);
+            accumulator = remainder;
+            previous = now;
+
+            if let Some(dropped) = dropped {
+                warn!(
+                    "session [{}] fell behind, dropping {:?} of ticks",
+                    self.id, dropped
+                );
+            }
+
+            if ticks > 0 {
+                let Some(inputs) = self.receive_commands() else {
+                    return info!("session [{}] stopped", self.id);
+                };
+                self.tick(inputs, ticks).await;
+            }
+
+            tokio::time::sleep(TICK_DURATION.saturating_sub(accumulator)).await;
+        }
+    }
+
+    /// Take in the queued commands, returning the inputs to carry out or
+    /// [`None`] once the session has been removed from the server
+    fn receive_commands(&mut self) -> Option<Vec<(String, PlayerInput)>> {
+        let mut inputs = Vec::new();
+
+        loop {
+            match self.commands.try_recv() {
+                Ok(SessionCommand::Input { player, input }) => inputs.push((player, input)),
+                Ok(SessionCommand::Subscribe(client)) => {
+                    self.members.insert(client.id.clone(), client);
+                }
+                Ok(SessionCommand::Unsubscribe(client_id)) => {
+                    self.members.remove(&client_id);
+                }
+                Err(TryRecvError::Empty) => return Some(inputs),
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+
+    /// Run the ticks for the session and send the results to its clients
+    async fn tick(&mut self, inputs: Vec<(String, PlayerInput)>, ticks: u32) {
+        let mut gamestate = self.gamestate.lock().await;
+        let mut mode = self.mode.lock().await;
+        let mut replay = self.replay.lock().await;
+        let mut broadcasts = Vec::new();
+
+        // the recording starts once the session has been set up and begins ticking
+        if self.recording && replay.is_none() {
+            *replay = Some(ReplayRecorder::new(&gamestate, &mode));
+        }
+
+        for (player, input) in inputs {
+            input.apply(&player, &mut gamestate, &mut mode);
+
+            if let Some(recorder) = replay.as_mut() {
+                recorder.record(&gamestate, &player, input);
+            }
+        }
+
+        for _ in 0..ticks {
+            let events = mode.update(&mut gamestate);
+            let match_over = events.iter().any(replays::ends_match);
+
+            for event in events {
+                broadcasts.push(match event {
+                    // the clients need to redraw the map whenever a new one is loaded
+                    ModeEvent::MapLoaded => {
+                        convert_environment_to_broadcast(&gamestate.environment)
+                    }
+                    event => ServerEvent::Mode(event),
+                });
+            }
+
+            gamestate.tick();
+
+            broadcasts.extend(
+                gamestate
+                    .damage_events
+                    .iter()
+                    .cloned()
+                    .map(ServerEvent::Damage),
+            );
+
+            // every match gets its own replay, with the next one starting where it ended
+            if match_over {
+                if let Some(recorder) = replay.take() {
+                    replays::write_replay(&self.id, &recorder.finish(&gamestate));
+                    *replay = Some(ReplayRecorder::new(&gamestate, &mode));
+                }
+            }
+        }
+
+        // only the latest state is worth sending after catching up on several ticks
+        broadcasts.push(convert_gamestate_to_broadcast(&gamestate));
+
+        // bots are sent observations in place of the broadcasts
+        let bots = self
+            .members
+            .values()
+            .filter(|client| client.bot.is_some())
+            .map(|client| &client.id);
+        let bot_view = bots::BotView::new(&gamestate, bots);
+
+        drop(replay);
+        drop(mode);
+        drop(gamestate);
+
+        let bot_messages = self.observe_bots(bot_view).await;
+
+        let messages = broadcasts
+            .iter()
+            .map(|event| serde_json::to_string(event).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut gone = Vec::new();
+        for (client_id, client) in &self.members {
+            if let Some(messages) = bot_messages.get(client_id) {
+                bots::send_messages(client, messages);
+                continue;
+            }
+
+            for message in &messages {
+                match client.sender.try_send(Message::Text(message.clone())) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        warn!("[{}] is falling behind, dropping broadcasts", client_id);
+                        break;
+                    }
+                    Err(TrySendError::Closed(_)) => {
+                        gone.push(client_id.clone());
+                        break;
+                    }
+                }
+            }
+        }
+
+        for client_id in gone {
+            self.members.remove(&client_id);
+        }
+    }
+
+    async fn observe_bots(
+        &self,
+        mut view: bots::BotView,
+    ) -> HashMap<String, Vec<BotServerMessage>> {
+        let mut bot_messages = HashMap::new();
+
+        for (client_id, client) in &self.members {
+            if let Some(link) = &client.bot {
+                let messages = bots::observe(link, client_id, &mut view).await;
+                bot_messages.insert(client_id.clone(), messages);
+            }
+        }
+
+        bot_messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn due(ticks: u32, remainder: Duration, dropped: Option<Duration>) -> DueTicks {
+        DueTicks {
+            ticks,
+            remainder,
+            dropped,
+        }
+    }
+
+    #[test]
+    fn time_is_saved_up_until_a_tick_is_due() {
+        let half = TICK_DURATION / 2;
+
+        assert_eq!(due_ticks(Duration::ZERO, half), due(0, half, None));
+        assert_eq!(
+            due_ticks(half, TICK_DURATION - half),
+            due(1, Duration::ZERO, None)
+        );
+    }
+
+    #[test]
+    fn late_ticks_are_caught_up_on() {
+        let extra = Duration::from_millis(1);
+
+        assert_eq!(due_ticks(extra, TICK_DURATION * 3), due(3, extra, None));
+    }
+
+    #[test]
+    fn catching_up_is_capped() {
+        assert_eq!(
+            due_ticks(Duration::ZERO, TICK_DURATION * MAX_CATCH_UP_TICKS),
+            due(MAX_CATCH_UP_TICKS, Duration::ZERO, None)
+        );
+    }
+
+    #[test]
+    fn ticks_past_the_cap_are_dropped() {
+        let behind = TICK_DURATION * (MAX_CATCH_UP_TICKS + 3) + Duration::from_millis(1);
+
+        assert_eq!(
+            due_ticks(Duration::ZERO, behind),
+            due(
+                MAX_CATCH_UP_TICKS,
+                Duration::ZERO,
+                Some(TICK_DURATION * 3 + Duration::from_millis(1))
+            )
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use std::{collections::HashMap, sync::Arc};
 
-use axum::extract::ws::{Message, WebSocket};
-use futures::stream::SplitSink;
-use tokio::sync::Mutex;
+use axum::extract::ws::Message;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::bots::BotLink;
 
 pub type SharedServerState<T> = Arc<ServerState<T>>;
 
@@ -12,10 +13,13 @@ pub struct ServerState<T> {
     pub sessions: Arc<Mutex<HashMap<String, Session<T>>>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Client {
     pub id: String,
-    pub sender: Arc<Mutex<SplitSink<WebSocket, Message>>>,
+    /// Outbox of messages that are written to the websocket of the client
+    pub sender: mpsc::Sender<Message>,
+    /// Set for programs connected to the bot endpoint, which are sent observations instead
+    pub bot: Option<Arc<Mutex<BotLink>>>,
 }
 
 #[derive(Debug)]
@@ -48,6 +52,18 @@ impl<T: Default> Session<T> {
     }
 }
 
+/// Longest session ID that clients can choose for themselves
+const MAX_SESSION_ID_LENGTH: usize = 32;
+
+/// Whether a session ID chosen by a client can be used, which keeps it safe to put into file names
+pub(crate) fn is_valid_session_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_SESSION_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
 /// Generates a String of given length using characters that are valid for Session IDs
 ///
 /// This should effectively resolve to Session uniqueness when the length is
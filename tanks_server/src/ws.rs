@@ -1,5 +1,3 @@
-use std::sync::Arc;
-
 use axum::{
     extract::{
         ws::{Message, WebSocket},
@@ -8,18 +6,25 @@ use axum::{
     response::IntoResponse,
     Extension,
 };
-use futures::{
-    stream::{SplitSink, SplitStream},
-    StreamExt,
-};
+use futures::{stream::SplitStream, StreamExt};
+use nanorand::Rng;
 use serde::Deserialize;
-use tanks_core::common::player::Player;
-use tanks_events::ClientEvent;
-use tokio::sync::Mutex;
-use tracing::info;
+use tanks_core::{
+    assets::parse_environments_list,
+    common::gamestate::GameState,
+    generator::GeneratorConfig,
+    modes::{royale::BattleRoyale, GameMode},
+    replay::PlayerInput,
+};
+use tanks_events::{convert_environment_to_broadcast, ClientEvent, ServerEvent};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
 
 use crate::{
-    state::{generate_session_id, Client, Session, SharedServerState},
+    config, maps,
+    persistence::{SavedSession, SessionHandle},
+    replays, sessions,
+    state::{generate_session_id, is_valid_session_id, Client, Session, SharedServerState},
     SessionData,
 };
 
@@ -36,9 +41,10 @@ pub async fn websocket_handler(
     if state.clients.lock().await.get(&connection_id).is_none() {
         ws.on_upgrade(move |socket| {
             // By splitting we can send and receive at the same time.
-            let (sender, receiver) = socket.split();
+            let (sink, receiver) = socket.split();
+            let sender = sessions::spawn_writer(sink);
 
-            ClientRunner::new(state, Arc::new(Mutex::new(sender)), receiver, connection_id).run()
+            ClientRunner::new(state, sender, receiver, connection_id).run()
         })
     } else {
         format!("User [{}] Already Connected", connection_id).into_response()
@@ -47,7 +53,7 @@ pub async fn websocket_handler(
 
 struct ClientRunner<T> {
     state: SharedServerState<T>,
-    sender: Arc<Mutex<SplitSink<WebSocket, Message>>>,
+    sender: mpsc::Sender<Message>,
     receiver: SplitStream<WebSocket>,
     connection_id: String,
     cached_session: Option<String>,
@@ -56,7 +62,7 @@ struct ClientRunner<T> {
 impl ClientRunner<SessionData> {
     pub fn new(
         state: SharedServerState<SessionData>,
-        sender: Arc<Mutex<SplitSink<WebSocket, Message>>>,
+        sender: mpsc::Sender<Message>,
         receiver: SplitStream<WebSocket>,
         connection_id: String,
     ) -> Self {
@@ -89,13 +95,19 @@ impl ClientRunner<SessionData> {
     }
 
     async fn connect_client(&self) {
-        self.state.clients.lock().await.insert(
-            self.connection_id.clone(),
-            Client {
-                id: self.connection_id.clone(),
-                sender: self.sender.clone(),
-            },
-        );
+        self.state
+            .clients
+            .lock()
+            .await
+            .insert(self.connection_id.clone(), self.client());
+    }
+
+    fn client(&self) -> Client {
+        Client {
+            id: self.connection_id.clone(),
+            sender: self.sender.clone(),
+            bot: None,
+        }
     }
 
     async fn disconnect_client(&self) {
@@ -107,108 +119,198 @@ impl ClientRunner<SessionData> {
     }
 
     async fn leave_session(&self, session_id: &str) {
-        // remove the client from the session and check if the session become empty
-        let empty = if let Some(session) = self.state.sessions.lock().await.get_mut(session_id) {
-            session.set_client_status(&self.connection_id, false);
-            session.active_client_set().is_empty()
-        } else {
-            false
+        leave_session(&self.state, &self.connection_id, session_id).await;
+    }
+
+    /// Pass an input of the client to the session it is playing in
+    async fn apply_input(&self, input: PlayerInput) {
+        let Some(session_id) = &self.cached_session else {
+            return;
         };
 
-        if empty {
-            self.state.sessions.lock().await.remove(session_id);
+        if let Some(session) = self.state.sessions.lock().await.get(session_id) {
+            session.data.queue_input(&self.connection_id, input);
         }
     }
 
     async fn process_event(&mut self, event: ClientEvent) {
         match event {
-            ClientEvent::AimUpdate { angle } => {
-                let Some(session_id) = &self.cached_session else {
-                    return;
-                };
+            ClientEvent::AimUpdate { angle } => self.apply_input(PlayerInput::Aim(angle)).await,
+            ClientEvent::MovementUpdate { direction } => {
+                self.apply_input(PlayerInput::Move(direction)).await
+            }
+            ClientEvent::Shoot => self.apply_input(PlayerInput::Shoot).await,
+            ClientEvent::CreateSession => {
+                let new_session = self.create_session(None).await;
 
-                if let Some(session) = self.state.sessions.lock().await.get_mut(session_id) {
-                    session
-                        .data
-                        .gamestate
-                        .lock()
-                        .await
-                        .set_player_angle(&self.connection_id, angle);
-                }
+                sessions::start(&self.state, new_session).await;
             }
-            ClientEvent::MovementUpdate { direction } => {
-                let Some(session_id) = &self.cached_session else {
-                    return;
+            ClientEvent::StartCampaign => {
+                // without all of its levels there is no campaign to play
+                let campaign = match maps::new_campaign() {
+                    Ok(campaign) => campaign,
+                    Err(missing) => return error!("campaign level [{}] does not exist", missing),
                 };
 
-                if let Some(session) = self.state.sessions.lock().await.get_mut(session_id) {
-                    session
-                        .data
-                        .gamestate
-                        .lock()
-                        .await
-                        .set_player_movement(&self.connection_id, &direction);
-                }
+                let new_session = self.create_session(None).await;
+                *new_session.data.mode.lock().await = GameMode::Campaign(campaign);
+
+                sessions::start(&self.state, new_session).await;
             }
-            ClientEvent::Shoot => {
-                let Some(session_id) = &self.cached_session else {
-                    return;
+            ClientEvent::StartSurvival => {
+                // the waves come out of the spawners of the survival map
+                let survival = match maps::new_survival() {
+                    Ok(survival) => survival,
+                    Err(missing) => return error!("survival map [{}] does not exist", missing),
                 };
 
-                if let Some(session) = self.state.sessions.lock().await.get_mut(session_id) {
-                    session
-                        .data
-                        .gamestate
-                        .lock()
-                        .await
-                        .player_shoot(&self.connection_id);
-                }
+                let new_session = self.create_session(None).await;
+                *new_session.data.mode.lock().await = GameMode::Survival(Box::new(survival));
+
+                sessions::start(&self.state, new_session).await;
             }
-            ClientEvent::CreateSession => {
+            ClientEvent::StartBattleRoyale => {
                 let new_session = self.create_session(None).await;
 
-                self.state
-                    .sessions
-                    .lock()
-                    .await
-                    .insert(new_session.id.clone(), new_session);
+                *new_session.data.mode.lock().await = GameMode::BattleRoyale(BattleRoyale::new());
+
+                sessions::start(&self.state, new_session).await;
+            }
+            ClientEvent::StartRotation | ClientEvent::StartMapVote => {
+                let new_session = self.create_session(None).await;
+
+                let vote = matches!(event, ClientEvent::StartMapVote);
+                *new_session.data.mode.lock().await =
+                    GameMode::Rotation(Box::new(maps::new_rotation(vote)));
+
+                sessions::start(&self.state, new_session).await;
+            }
+            ClientEvent::MapVote(map) => self.apply_input(PlayerInput::Vote(map)).await,
+            ClientEvent::CreateRandomSession { seed } => {
+                let new_session = self.create_session(None).await;
+
+                let seed = seed.unwrap_or_else(|| nanorand::tls_rng().generate::<u64>());
+                let environment = GeneratorConfig::with_seed(seed).generate();
+                info!(
+                    "session [{}] generated map with seed {}",
+                    new_session.id, seed
+                );
+
+                let map_update = convert_environment_to_broadcast(&environment);
+                let mut gamestate = new_session.data.gamestate.lock().await;
+                gamestate.environment = environment;
+                gamestate.respawn_players();
+                drop(gamestate);
+
+                sessions::start(&self.state, new_session).await;
+
+                self.send_event(&map_update).await;
+                self.send_event(&ServerEvent::MapSeed(seed)).await;
+            }
+            ClientEvent::TestMap { map } => {
+                let environment = match parse_environments_list(map.as_bytes()) {
+                    Ok(maps) => match maps.into_iter().next() {
+                        Some((_, environment)) => environment,
+                        None => return error!("test map from [{}] was empty", self.connection_id),
+                    },
+                    Err(e) => {
+                        return error!("test map from [{}] is invalid: {}", self.connection_id, e)
+                    }
+                };
+
+                let mut new_session = self.create_session(None).await;
+                new_session.data.private = true;
+
+                let map_update = convert_environment_to_broadcast(&environment);
+                let mut gamestate = new_session.data.gamestate.lock().await;
+                gamestate.environment = environment;
+                gamestate.respawn_players();
+                drop(gamestate);
+
+                sessions::start(&self.state, new_session).await;
+
+                self.send_event(&map_update).await;
             }
             ClientEvent::JoinSession(session_id) => {
+                if !is_valid_session_id(&session_id) {
+                    return warn!(
+                        "[{}] cannot join session with invalid ID [{}]",
+                        self.connection_id, session_id
+                    );
+                }
+
                 let mut lock = self.state.sessions.lock().await;
 
+                if lock.get(&session_id).is_some_and(|s| s.data.private) {
+                    return info!(
+                        "[{}] cannot join private session [{}]",
+                        self.connection_id, session_id
+                    );
+                }
+
                 if let Some(session) = lock.get_mut(&session_id) {
-                    session
-                        .client_statuses
-                        .insert(self.connection_id.clone(), true);
+                    session.add_client(self.client());
 
-                    session.data.gamestate.lock().await.players.insert(
-                        self.connection_id.clone(),
-                        Player::new(self.connection_id.clone()),
-                    );
+                    // a Tank is kept for players rejoining a session, such as after a restart
+                    session
+                        .data
+                        .queue_input(&self.connection_id, PlayerInput::Join);
 
+                    let gamestate = session.data.gamestate.clone();
                     self.cached_session = Some(session.id.clone());
+                    drop(lock);
+
+                    // catch the client up on the map that is already being played
+                    let map_update =
+                        convert_environment_to_broadcast(&gamestate.lock().await.environment);
+                    self.send_event(&map_update).await;
                 } else {
                     drop(lock);
 
-                    let mut session = self.create_session(Some(session_id)).await;
+                    let session = self.create_session(Some(session_id)).await;
 
-                    session
-                        .client_statuses
-                        .insert(self.connection_id.clone(), true);
+                    sessions::start(&self.state, session).await;
+                }
+            }
+            ClientEvent::CloneSession => {
+                let Some(session_id) = self.cached_session.clone() else {
+                    return;
+                };
 
-                    session.data.gamestate.lock().await.players.insert(
-                        self.connection_id.clone(),
-                        Player::new(self.connection_id.clone()),
-                    );
+                let handle = self
+                    .state
+                    .sessions
+                    .lock()
+                    .await
+                    .get(&session_id)
+                    .map(SessionHandle::new);
+                let Some(handle) = handle else {
+                    return;
+                };
+                let snapshot = SavedSession::from_handle(&handle).await.snapshot;
 
-                    self.cached_session = Some(session.id.clone());
+                self.leave_session(&session_id).await;
+
+                let mut new_session = self.create_session(None).await;
+                new_session.data.private = true;
 
-                    self.state
-                        .sessions
-                        .lock()
-                        .await
-                        .insert(session.id.clone(), session);
+                let map_update = convert_environment_to_broadcast(&snapshot.gamestate.environment);
+                *new_session.data.mode.lock().await = snapshot.mode;
+                let mut gamestate = new_session.data.gamestate.lock().await;
+                *gamestate = snapshot.gamestate;
+                if !gamestate.players.contains_key(&self.connection_id) {
+                    gamestate.add_player(self.connection_id.clone());
                 }
+                drop(gamestate);
+
+                info!(
+                    "session [{}] cloned into sandbox [{}]",
+                    session_id, new_session.id
+                );
+
+                sessions::start(&self.state, new_session).await;
+
+                self.send_event(&map_update).await;
             }
             ClientEvent::LeaveSession => {
                 if let Some(session_id) = &self.cached_session {
@@ -218,26 +320,70 @@ impl ClientRunner<SessionData> {
         }
     }
 
+    async fn send_event(&self, event: &ServerEvent) {
+        let message = Message::Text(serde_json::to_string(event).unwrap());
+
+        if let Err(e) = self.sender.send(message).await {
+            error!("transmission error: [{}]", e);
+        }
+    }
+
     async fn create_session(
         &mut self,
         reserved_id: Option<String>,
     ) -> Session<crate::SessionContainer> {
-        let session_id = reserved_id.unwrap_or_else(|| generate_session_id());
-        let mut session = Session::<SessionData>::new(session_id.clone());
+        let session_id = reserved_id.unwrap_or_else(generate_session_id);
+        self.cached_session = Some(session_id.clone());
 
-        session
-            .client_statuses
-            .insert(self.connection_id.clone(), true);
+        new_session(session_id, self.client()).await
+    }
+}
+
+/// A session with a Tank for the client that asked for it
+pub(crate) async fn new_session(session_id: String, client: Client) -> Session<SessionData> {
+    let mut session = Session::<SessionData>::new(session_id.clone());
+
+    let mut gamestate = session.data.gamestate.lock().await;
+    *gamestate = GameState::new(nanorand::tls_rng().generate::<u64>());
+    gamestate.health = config::HEALTH.clone();
+    gamestate.add_player(client.id.clone());
+    drop(gamestate);
+
+    session.add_client(client);
+
+    info!("new session [{}] created", session_id);
 
-        session.data.gamestate.lock().await.players.insert(
-            self.connection_id.clone(),
-            Player::new(self.connection_id.clone()),
-        );
+    session
+}
+
+/// Remove the client from the session, ending the session once nobody is left in it
+pub(crate) async fn leave_session(
+    state: &SharedServerState<SessionData>,
+    client_id: &str,
+    session_id: &str,
+) {
+    if let Some(session) = state.sessions.lock().await.get_mut(session_id) {
+        session.remove_client(client_id);
+    }
 
-        info!("new session [{}] created", session_id);
+    remove_if_empty(state, session_id).await;
+}
 
-        self.cached_session = Some(session_id);
+/// Remove a session once it has no clients left, which stops its task
+pub(crate) async fn remove_if_empty(state: &SharedServerState<SessionData>, session_id: &str) {
+    let removed = {
+        let mut sessions = state.sessions.lock().await;
+        match sessions.get(session_id) {
+            Some(session) if session.active_client_set().is_empty() => sessions.remove(session_id),
+            _ => None,
+        }
+    };
 
-        session
+    // the match is cut short, but what was played so far is still worth keeping
+    if let Some(session) = removed {
+        if let Some(recorder) = session.data.replay.lock().await.take() {
+            let gamestate = session.data.gamestate.lock().await;
+            replays::write_replay(session_id, &recorder.finish(&gamestate));
+        }
     }
 }
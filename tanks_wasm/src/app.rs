@@ -1,16 +1,20 @@
 use crate::{
+    editor::{render_editor, EDITOR},
     interface::{CONNECTION_STATE, GAME_STATE},
     login::render_login,
     utils::{get_block_size, Prepared},
+    viewer::{render_viewer, VIEWER},
 };
 use std::{
     collections::{HashMap, HashSet},
     f64::consts::PI,
 };
+use tanks_core::modes::ModeEvent;
 use tanks_core::{
     common::{
-        constants::{BULLET_RADIUS, MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH},
+        constants::{BULLET_RADIUS, MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH, PLAYER_RADIUS},
         environment::{Environment, Tile},
+        zone::SafeZone,
     },
     utils::Vector2,
 };
@@ -27,6 +31,14 @@ pub struct ClientGameState {
     pub player_data: HashMap<String, TankWrapper>,
     pub projectile_data: Vec<Vector2>,
     pub map_landmarks: Environment,
+    /// Message shown across the screen, such as the progress of a campaign
+    pub banner: Option<String>,
+    /// Status line drawn in the corner, such as the current survival wave
+    pub hud: Option<String>,
+    /// Safe area of a battle royale
+    pub zone: Option<SafeZone>,
+    /// Maps that can be voted for, chosen with the number keys
+    pub vote: Option<Vec<String>>,
 }
 
 impl ClientGameState {
@@ -40,6 +52,10 @@ impl ClientGameState {
                 .collect(),
             projectile_data: Vec::new(),
             map_landmarks: Environment::default(),
+            banner: None,
+            hud: None,
+            zone: None,
+            vote: None,
         }
     }
 
@@ -53,15 +69,22 @@ impl ClientGameState {
 
 pub fn handle_server_event(event: ServerEvent, game_state: &mut ClientGameState) {
     match event {
-        ServerEvent::GameState { bullets, tanks } => {
+        ServerEvent::GameState {
+            bullets,
+            tanks,
+            zone,
+        } => {
+            game_state.zone = zone;
+
+            // forget about tanks that are no longer in the game, such as bots from a previous level
+            game_state
+                .player_data
+                .retain(|id, _| tanks.iter().any(|tank| &tank.id == id));
+
             // either update the player or add them
-            for tank in tanks {
-                if let Some(player_data) = game_state.player_data.get_mut(&tank.id) {
-                    *player_data = tank;
-                    player_data.position = player_data.position.scale(get_block_size());
-                } else {
-                    game_state.player_data.insert(tank.id.clone(), tank);
-                }
+            for mut tank in tanks {
+                tank.position = tank.position.scale(get_block_size());
+                game_state.player_data.insert(tank.id.clone(), tank);
             }
 
             game_state.projectile_data = bullets
@@ -69,9 +92,98 @@ pub fn handle_server_event(event: ServerEvent, game_state: &mut ClientGameState)
                 .map(|pos| pos.position.scale(get_block_size()))
                 .collect()
         }
+        ServerEvent::MapUpdate { tiles } => {
+            game_state.map_landmarks.tiles = tiles.into_iter().collect();
+        }
+        ServerEvent::Mode(event) => match event {
+            ModeEvent::MapLoaded => {}
+            ModeEvent::LevelStarted { name, lives, .. } => {
+                game_state.banner = Some(format!("{} ({} lives)", name, lives));
+            }
+            ModeEvent::MissionComplete { level } => {
+                game_state.banner = Some(format!("Mission {} Complete!", level + 1));
+            }
+            ModeEvent::LifeLost { lives } => {
+                game_state.banner = Some(format!("{} lives left", lives));
+            }
+            ModeEvent::CampaignComplete => {
+                game_state.banner = Some(String::from("Campaign Complete!"));
+            }
+            ModeEvent::CampaignFailed { level } => {
+                game_state.banner = Some(format!("Campaign Failed on Mission {}", level + 1));
+            }
+            ModeEvent::WaveStarted { wave } => {
+                game_state.banner = Some(format!("Wave {}", wave));
+            }
+            ModeEvent::WaveCleared { wave } => {
+                game_state.banner = Some(format!("Wave {} Cleared!", wave));
+            }
+            ModeEvent::SurvivalOver { wave, .. } => {
+                game_state.banner = Some(format!("Overrun on Wave {}", wave));
+            }
+            ModeEvent::RoyaleStarted { round } => {
+                game_state.banner = Some(format!("Round {}", round));
+            }
+            ModeEvent::RoyaleWinner { player } => {
+                game_state.banner = Some(match player {
+                    Some(player) => format!("{} Wins!", player),
+                    None => String::from("Nobody Survived"),
+                });
+            }
+            ModeEvent::MatchStarted { map } => {
+                game_state.vote = None;
+                game_state.hud = None;
+                game_state.banner = map.map(|map| format!("Now Playing {}", map));
+            }
+            ModeEvent::MatchEnded { winner } => {
+                game_state.banner = Some(match winner {
+                    Some(player) => format!("{} Wins!", player),
+                    None => String::from("Nobody Survived"),
+                });
+            }
+            ModeEvent::VoteStarted { candidates, ticks } => {
+                let choices = candidates
+                    .iter()
+                    .enumerate()
+                    .map(|(i, map)| format!("[{}] {}", i + 1, map))
+                    .collect::<Vec<_>>();
+                game_state.banner = Some(format!("Vote for the next map: {}", choices.join("  ")));
+                // there are 60 GameState ticks every second
+                game_state.hud = Some(format!("Vote ends in {}s", ticks.div_ceil(60)));
+                game_state.vote = Some(candidates);
+            }
+            ModeEvent::VoteStatus { votes, ticks } => {
+                let tally = votes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (map, count))| format!("[{}] {} ({})", i + 1, map, count))
+                    .collect::<Vec<_>>();
+                game_state.banner = Some(format!("Vote for the next map: {}", tally.join("  ")));
+                game_state.hud = Some(format!("Vote ends in {}s", ticks.div_ceil(60)));
+            }
+            ModeEvent::WaveStatus {
+                wave,
+                remaining,
+                highest_wave,
+            } => {
+                game_state.hud = Some(format!(
+                    "Wave {} | Enemies {} | Best {}",
+                    wave, remaining, highest_wave
+                ));
+            }
+        },
+        ServerEvent::MapSeed(seed) => {
+            game_state.banner = Some(format!("Map Seed {}", seed));
+        }
         ServerEvent::PlayerDisconnect { player } => {
             game_state.player_data.remove(&player);
         }
+        ServerEvent::Damage(damage) => {
+            // show the hit right away instead of waiting for the next state update
+            if let Some(tank) = game_state.player_data.get_mut(&damage.target) {
+                tank.hit_points = damage.hit_points;
+            }
+        }
         ServerEvent::BulletExplode(_) => {
             // display some kind of animation to show that
             // the bullets exploded at these coordinates
@@ -91,11 +203,23 @@ pub fn render(element: &HtmlCanvasElement, context: &CanvasRenderingContext2d) {
     if connected {
         GAME_STATE.with(|state| render_game(context, &state.borrow()))
     } else {
-        render_login(context)
+        EDITOR.with(|editor| match editor.borrow().as_ref() {
+            Some(editor) => render_editor(context, editor),
+            None => VIEWER.with(|viewer| match viewer.borrow_mut().as_mut() {
+                Some(viewer) => render_viewer(context, viewer),
+                None => render_login(context),
+            }),
+        })
     }
 }
 
 fn render_game(context: &CanvasRenderingContext2d, game_state: &ClientGameState) {
+    render_world(context, game_state);
+    render_overlay(context, game_state);
+}
+
+/// Draw the map along with everything on it
+pub fn render_world(context: &CanvasRenderingContext2d, game_state: &ClientGameState) {
     context.save();
 
     let block_size = get_block_size();
@@ -104,24 +228,36 @@ fn render_game(context: &CanvasRenderingContext2d, game_state: &ClientGameState)
     for col in 0..MAP_BLOCK_WIDTH {
         for row in 0..MAP_BLOCK_HEIGHT {
             context.set_fill_style(&colors[(col + row).rem_euclid(2)].into());
-
-            if let Some(tile) = game_state.map_landmarks.tiles.get(&(row, col)) {
-                match tile {
-                    Tile::Empty => context.set_fill_style(&"grey".into()),
-                    Tile::DesructableWall(_) => context.set_fill_style(&"orange".into()),
-                    Tile::IndestructableWall(_) => context.set_fill_style(&"brown".into()),
-                }
-            }
-
             context.fill_rect(
                 block_size * col as f64,
                 block_size * row as f64,
                 block_size,
                 block_size,
             );
+
+            if let Some(tile) = game_state.map_landmarks.tiles.get(&(row, col)) {
+                render_tile(context, tile, (row, col), block_size);
+            }
+
+            // tint the deadly tiles that are outside of the safe zone
+            if let Some(zone) = &game_state.zone {
+                if !zone.is_tile_safe(row, col) {
+                    context.set_fill_style(&"rgba(200, 30, 30, 0.4)".into());
+                    context.fill_rect(
+                        block_size * col as f64,
+                        block_size * row as f64,
+                        block_size,
+                        block_size,
+                    );
+                }
+            }
         }
     }
 
+    if let Some(zone) = &game_state.zone {
+        render_zone(context, zone, block_size);
+    }
+
     context.set_fill_style(&"grey".into());
     for bullet in &game_state.projectile_data {
         context.begin_path();
@@ -137,35 +273,41 @@ fn render_game(context: &CanvasRenderingContext2d, game_state: &ClientGameState)
         context.fill();
     }
 
-    for (player, tank_data) in &game_state.player_data {
+    for (player, tank_data) in game_state.player_data.iter().filter(|(_, t)| t.alive) {
         context.save();
 
         context
             .translate(tank_data.position.x, tank_data.position.y)
             .unwrap();
 
-        context
-            .rotate(tank_data.movement.y.atan2(tank_data.movement.x))
-            .unwrap();
+        context.rotate(tank_data.hull_angle).unwrap();
+
+        // the hull is drawn at the size it collides with
+        let half = block_size * PLAYER_RADIUS;
 
         context.set_fill_style(&"red".into());
-        context.fill_rect(-block_size / 2.0, -block_size / 2.0, block_size, block_size);
+        context.fill_rect(-half, -half, 2.0 * half, 2.0 * half);
 
         context.set_stroke_style(&"black".into());
         context.set_line_width(8.0);
 
         context.begin_path();
-        context.move_to(-block_size / 2.0, -block_size / 2.0);
-        context.line_to(block_size / 2.0, -block_size / 2.0);
+        context.move_to(-half, -half);
+        context.line_to(half, -half);
         context.stroke();
 
         context.begin_path();
-        context.move_to(-block_size / 2.0, block_size / 2.0);
-        context.line_to(block_size / 2.0, block_size / 2.0);
+        context.move_to(-half, half);
+        context.line_to(half, half);
         context.stroke();
 
         context.restore();
 
+        // Tanks that can take more than one hit show how many hit points they have left
+        if tank_data.max_hit_points > 1 {
+            render_health_bar(context, tank_data, block_size);
+        }
+
         // I think we want this to be a fixed pixel size so that you can always see the name
         context.set_font("20px monospace");
         context.set_text_align("center");
@@ -188,3 +330,133 @@ fn render_game(context: &CanvasRenderingContext2d, game_state: &ClientGameState)
 
     context.restore();
 }
+
+/// Draw the messages that stay in place on the screen
+pub fn render_overlay(context: &CanvasRenderingContext2d, game_state: &ClientGameState) {
+    context.save();
+
+    let block_size = get_block_size();
+
+    if let Some(banner) = &game_state.banner {
+        context.set_font("40px monospace");
+        context.set_text_align("center");
+        context.set_fill_style(&"white".into());
+        context
+            .fill_text(
+                banner,
+                block_size * MAP_BLOCK_WIDTH as f64 / 2.0,
+                block_size * 1.5,
+            )
+            .expect("text could not be drawn");
+    }
+
+    if let Some(hud) = &game_state.hud {
+        context.set_font("20px monospace");
+        context.set_text_align("left");
+        context.set_fill_style(&"white".into());
+        context
+            .fill_text(hud, block_size * 0.25, block_size * 0.5)
+            .expect("text could not be drawn");
+    }
+
+    context.restore();
+}
+
+/// Draw a tile over the ground of the (row, column) location
+fn render_health_bar(context: &CanvasRenderingContext2d, tank_data: &TankWrapper, block_size: f64) {
+    let (width, height) = (block_size, block_size * 0.12);
+    let x = tank_data.position.x - width / 2.0;
+    let y = tank_data.position.y - block_size * 0.75;
+    let fraction = tank_data.hit_points as f64 / tank_data.max_hit_points as f64;
+
+    context.set_fill_style(&"#300".into());
+    context.fill_rect(x, y, width, height);
+
+    context.set_fill_style(&"#3C3".into());
+    context.fill_rect(x, y, width * fraction.min(1.0), height);
+}
+
+pub fn render_tile(
+    context: &CanvasRenderingContext2d,
+    tile: &Tile,
+    (row, col): (usize, usize),
+    block_size: f64,
+) {
+    let (x, y) = (block_size * col as f64, block_size * row as f64);
+
+    let color = match tile {
+        Tile::Empty => "grey",
+        Tile::DesructableWall(_) => "orange",
+        Tile::IndestructableWall(_) => "brown",
+        Tile::Hole => "#0B0E14",
+        Tile::Mud => "#6B5030",
+        Tile::Water => "#2F6DB5",
+        Tile::Ice => "#CDEBF7",
+        Tile::Teleporter(_) => "#3A2E5C",
+    };
+    context.set_fill_style(&color.into());
+    context.fill_rect(x, y, block_size, block_size);
+
+    // a glowing ring with the letter of the channel, so the pairs can be told apart
+    if let Tile::Teleporter(channel) = tile {
+        context.set_stroke_style(&"#B18CFF".into());
+        context.set_line_width(3.0);
+        context.begin_path();
+        context
+            .arc(
+                x + block_size / 2.0,
+                y + block_size / 2.0,
+                block_size / 3.0,
+                0.0,
+                2.0 * PI,
+            )
+            .expect("teleporter could not be drawn");
+        context.stroke();
+
+        let letter = char::from_u32('T' as u32 + *channel as u32).unwrap_or('?');
+        context.set_font("16px monospace");
+        context.set_text_align("center");
+        context.set_fill_style(&"white".into());
+        context
+            .fill_text(
+                &letter.to_string(),
+                x + block_size / 2.0,
+                y + block_size * 0.6,
+            )
+            .expect("text could not be drawn");
+    }
+}
+
+/// Draw the boundary of the safe zone along with the time until it contracts
+fn render_zone(context: &CanvasRenderingContext2d, zone: &SafeZone, block_size: f64) {
+    context.set_stroke_style(&"white".into());
+    context.set_line_width(3.0);
+    context.begin_path();
+    context
+        .arc(
+            zone.center.x * block_size,
+            zone.center.y * block_size,
+            zone.radius * block_size,
+            0.0,
+            2.0 * PI,
+        )
+        .expect("zone could not be drawn");
+    context.stroke();
+
+    let countdown = match zone.is_contracting() {
+        true => String::from("Zone closing!"),
+        // there are 60 GameState ticks every second
+        false => format!("Zone closes in {}s", zone.countdown.div_ceil(60)),
+    };
+
+    context.set_font("20px monospace");
+    context.set_text_align("right");
+    context.set_fill_style(&"white".into());
+    context
+        .fill_text(
+            &countdown,
+            block_size * (MAP_BLOCK_WIDTH as f64 - 0.25),
+            block_size * 0.5,
+        )
+        .expect("text could not be drawn");
+}
@@ -0,0 +1,202 @@
+//! Editor screen for painting maps, opened by visiting the page with `#editor`
+//!
+//! Keys pick the brush using the same symbols as a MapData file, so `1`-`5` paint
+//! indestructable walls, `6`-`9` destructable walls, `x` empty tiles, `o` holes, `m` mud,
+//! `w` water, `i` ice, `T`-`W` teleporter pairs, `p` spawns and `.` erases.
+//! Holding the mouse paints the tile under the cursor, `Enter` test plays the map in a
+//! private session, `Escape` returns from test play and `e` downloads the map as a `.ed` file.
+
+use std::cell::RefCell;
+
+use tanks_core::{
+    assets::{place_symbol, write_environments_file},
+    common::{
+        constants::{MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH},
+        environment::{Environment, Tile},
+    },
+    utils::Vector2,
+    validation::validate_environment,
+};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Blob, BlobPropertyBag, CanvasRenderingContext2d, HtmlAnchorElement, Url};
+
+use crate::{
+    app::render_tile,
+    utils::{document, get_block_size, js_window},
+};
+
+/// Name that the edited map is exported under
+pub const EDITOR_MAP_NAME: &str = "custom";
+
+thread_local! {
+  /// The map being edited, when the page was opened with `#editor`
+  pub static EDITOR: RefCell<Option<MapEditor>> = const { RefCell::new(None) };
+}
+
+pub struct MapEditor {
+    pub environment: Environment,
+    /// Map symbol that gets painted onto the tiles
+    pub brush: char,
+    /// Whether the mouse is held down
+    pub painting: bool,
+}
+
+impl Default for MapEditor {
+    fn default() -> Self {
+        Self {
+            environment: Environment {
+                width: MAP_BLOCK_WIDTH,
+                height: MAP_BLOCK_HEIGHT,
+                ..Default::default()
+            },
+            brush: '1',
+            painting: false,
+        }
+    }
+}
+
+impl MapEditor {
+    /// Paint the brush onto the tile under the mouse position
+    pub fn paint(&mut self, mouse_pos: Vector2) {
+        let block_size = get_block_size();
+        let (row, col) = (
+            (mouse_pos.y / block_size).floor(),
+            (mouse_pos.x / block_size).floor(),
+        );
+
+        if row < 0.0 || col < 0.0 {
+            return;
+        }
+
+        let location = (row as usize, col as usize);
+        if location.0 >= MAP_BLOCK_HEIGHT || location.1 >= MAP_BLOCK_WIDTH {
+            return;
+        }
+
+        // a tile only ever holds the most recent symbol painted onto it
+        let environment = &mut self.environment;
+        environment.tiles.remove(&location);
+        environment.spawns.retain(|spawn| *spawn != location);
+        environment.spawners.retain(|spawner| *spawner != location);
+        environment.team_zones.remove(&location);
+        environment
+            .enemies
+            .retain(|enemy| enemy.location != location);
+        environment
+            .pickups
+            .retain(|pickup| pickup.location != location);
+
+        place_symbol(environment, location, self.brush);
+    }
+
+    /// Switch to the brush for a key, if the key is a map symbol
+    pub fn select_brush(&mut self, key: &str) -> bool {
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(sym), None) if place_symbol(&mut Environment::default(), (0, 0), sym) => {
+                self.brush = sym;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The map in the MapData format read by `parse_environments_file`
+    pub fn export(&self) -> String {
+        write_environments_file([(&String::from(EDITOR_MAP_NAME), &self.environment)])
+    }
+
+    /// Save the map to the computer of the designer
+    pub fn download(&self) -> Result<(), JsValue> {
+        let parts = js_sys::Array::of1(&JsValue::from_str(&self.export()));
+        let blob = Blob::new_with_str_sequence_and_options(
+            &parts,
+            BlobPropertyBag::new().type_("text/plain"),
+        )?;
+        let url = Url::create_object_url_with_blob(&blob)?;
+
+        let link = document()
+            .create_element("a")?
+            .dyn_into::<HtmlAnchorElement>()?;
+        link.set_href(&url);
+        link.set_download(&format!("{}.ed", EDITOR_MAP_NAME));
+        link.click();
+
+        Url::revoke_object_url(&url)
+    }
+}
+
+/// Whether the page was opened to edit maps
+pub fn is_editor_page() -> bool {
+    js_window().location().hash().unwrap_or_default() == "#editor"
+}
+
+pub fn render_editor(context: &CanvasRenderingContext2d, editor: &MapEditor) {
+    context.save();
+
+    let block_size = get_block_size();
+    let environment = &editor.environment;
+
+    context.set_font("16px monospace");
+    context.set_text_align("center");
+
+    let colors = ["#5C6784", "#1D263B"];
+    for col in 0..MAP_BLOCK_WIDTH {
+        for row in 0..MAP_BLOCK_HEIGHT {
+            let (x, y) = (block_size * col as f64, block_size * row as f64);
+
+            context.set_fill_style(&colors[(col + row).rem_euclid(2)].into());
+            context.fill_rect(x, y, block_size, block_size);
+            if let Some(tile) = environment.tiles.get(&(row, col)) {
+                render_tile(context, tile, (row, col), block_size);
+            }
+
+            // the elevation is not visible in game, so label it while editing
+            let elevation = match environment.tiles.get(&(row, col)) {
+                Some(Tile::IndestructableWall(elevation))
+                | Some(Tile::DesructableWall((_, elevation))) => Some(*elevation),
+                _ => None,
+            };
+            if let Some(elevation) = elevation {
+                context.set_fill_style(&"white".into());
+                context
+                    .fill_text(
+                        &elevation.to_string(),
+                        x + block_size / 2.0,
+                        y + block_size / 2.0,
+                    )
+                    .expect("text could not be drawn");
+            }
+        }
+    }
+
+    context.set_fill_style(&"red".into());
+    for (row, col) in &environment.spawns {
+        context.begin_path();
+        context
+            .arc(
+                block_size * (*col as f64 + 0.5),
+                block_size * (*row as f64 + 0.5),
+                block_size / 3.0,
+                0.0,
+                2.0 * std::f64::consts::PI,
+            )
+            .expect("spawn could not be drawn");
+        context.fill();
+    }
+
+    let issues = validate_environment(environment);
+    let status = match issues.first() {
+        Some(issue) => format!("brush [{}] | {}", editor.brush, issue),
+        None => format!("brush [{}] | Enter to test play, e to export", editor.brush),
+    };
+
+    context.set_font("20px monospace");
+    context.set_text_align("left");
+    context.set_fill_style(&"white".into());
+    context
+        .fill_text(&status, block_size * 0.25, block_size * 0.5)
+        .expect("text could not be drawn");
+
+    context.restore();
+}
@@ -1,9 +1,11 @@
 use crate::{
     app::{handle_server_event, ClientGameState},
+    editor::EDITOR,
     log,
     login::process_login_keyevent,
     socket::setup_websocket_listeners,
-    utils::{fetch_or_create_canvas, js_window, Canvas, Prepared},
+    utils::{fetch_or_create_canvas, get_websocket_uri, js_window, Canvas, Prepared},
+    viewer::VIEWER,
 };
 use std::{cell::RefCell, rc::Rc};
 use tanks_core::utils::Vector2;
@@ -57,6 +59,14 @@ pub fn setup_window_listeners() {
     let mousemove_callback = Closure::wrap(Box::new(move |event: MouseEvent| {
         let mouse_pos = Vector2::new(event.offset_x().into(), event.offset_y().into());
 
+        if !is_connected() {
+            EDITOR.with(|editor| {
+                if let Some(editor) = editor.borrow_mut().as_mut().filter(|e| e.painting) {
+                    editor.paint(mouse_pos);
+                }
+            });
+        }
+
         GAME_STATE.with(|state| {
             state.borrow_mut().mouse_pos = mouse_pos;
             let state = state.borrow();
@@ -83,7 +93,28 @@ pub fn setup_window_listeners() {
     mousemove_callback.forget();
 
     // Mouse Click Click
-    let click_callback = Closure::wrap(Box::new(move |_: MouseEvent| {
+    let click_callback = Closure::wrap(Box::new(move |event: MouseEvent| {
+        if !is_connected() {
+            EDITOR.with(|editor| {
+                if let Some(editor) = editor.borrow_mut().as_mut() {
+                    editor.painting = true;
+                    editor.paint(Vector2::new(
+                        event.offset_x().into(),
+                        event.offset_y().into(),
+                    ));
+                }
+            });
+
+            VIEWER.with(|viewer| {
+                if let Some(viewer) = viewer.borrow_mut().as_mut() {
+                    viewer.click(Vector2::new(
+                        event.offset_x().into(),
+                        event.offset_y().into(),
+                    ));
+                }
+            });
+        }
+
         CONNECTION_STATE.with(|state| {
             if let Some(ws) = &state.borrow_mut().ws {
                 if ws.is_ready() {
@@ -98,13 +129,55 @@ pub fn setup_window_listeners() {
         .expect("failed to add listener");
     click_callback.forget();
 
+    // Mouse Release Callback
+    let release_callback = Closure::wrap(Box::new(move |_: MouseEvent| {
+        EDITOR.with(|editor| {
+            if let Some(editor) = editor.borrow_mut().as_mut() {
+                editor.painting = false;
+            }
+        });
+    }) as Box<dyn FnMut(_)>);
+    js_window()
+        .add_event_listener_with_callback("mouseup", release_callback.as_ref().unchecked_ref())
+        .expect("failed to add listener");
+    release_callback.forget();
+
     // Key Pressing Callback
     let keydown_callback = Closure::wrap(Box::new(move |event: KeyboardEvent| {
         CONNECTION_STATE.with(|state| {
             let mut connection_state = state.borrow_mut();
             match &connection_state.ws {
+                // leave test play and go back to editing the map
+                Some(ws) if event.key() == "Escape" && EDITOR.with(|e| e.borrow().is_some()) => {
+                    ws.close().expect("websocket closed");
+                    connection_state.ws = None;
+                }
                 Some(ws) => {
                     if ws.is_ready() {
+                        // the backtick copies the session into a private sandbox to experiment in
+                        if event.key() == "`" {
+                            ws.send_with_str(
+                                &serde_json::to_string(&ClientEvent::CloneSession).unwrap(),
+                            )
+                            .expect("websocket sent");
+                            return;
+                        }
+
+                        // the number keys pick a map while a vote is running
+                        let choice = GAME_STATE.with(|gstate| {
+                            let gstate = gstate.borrow();
+                            let index = event.key().parse::<usize>().ok()?.checked_sub(1)?;
+                            gstate.vote.as_ref()?.get(index).cloned()
+                        });
+
+                        if let Some(map) = choice {
+                            ws.send_with_str(
+                                &serde_json::to_string(&ClientEvent::MapVote(map)).unwrap(),
+                            )
+                            .expect("websocket sent");
+                            return;
+                        }
+
                         let movement_update = ClientEvent::MovementUpdate {
                             direction: GAME_STATE.with(|gstate| {
                                 gstate.borrow_mut().keysdown.insert(event.key());
@@ -121,6 +194,23 @@ pub fn setup_window_listeners() {
                             .expect("websocket sent");
                     }
                 }
+                None if VIEWER.with(|v| v.borrow().is_some()) => VIEWER.with(|viewer| {
+                    if let Some(viewer) = viewer.borrow_mut().as_mut() {
+                        viewer.press(&event.key());
+                    }
+                }),
+                None if EDITOR.with(|e| e.borrow().is_some()) => {
+                    if let Some((username, ws)) = process_editor_keyevent(event) {
+                        setup_websocket_listeners(&ws, |event| {
+                            GAME_STATE
+                                .with(|state| handle_server_event(event, &mut state.borrow_mut()))
+                        });
+
+                        connection_state.ws = Some(ws);
+                        GAME_STATE
+                            .with(|data| *data.borrow_mut() = ClientGameState::new(&username));
+                    }
+                }
                 None => {
                     if let Some((username, ws)) = process_login_keyevent(event) {
                         setup_websocket_listeners(&ws, |event| {
@@ -170,6 +260,40 @@ pub fn setup_window_listeners() {
     keyup_callback.forget();
 }
 
+/// Whether a game is being played over the websocket
+fn is_connected() -> bool {
+    CONNECTION_STATE.with(|state| state.borrow().ws.as_ref().is_some_and(|ws| ws.is_ready()))
+}
+
+/// Handle a key pressed in the map editor, connecting to test play the map on `Enter`
+fn process_editor_keyevent(event: KeyboardEvent) -> Option<(String, WebSocket)> {
+    EDITOR.with(|editor| {
+        let mut editor = editor.borrow_mut();
+        let editor = editor.as_mut()?;
+
+        match event.key().as_str() {
+            "Enter" => {
+                // designers do not pick a name, so avoid clashing with anyone else testing
+                let username = format!("designer{}", (js_sys::Math::random() * 1e6) as u32);
+                let ws = WebSocket::new(&get_websocket_uri(&username))
+                    .expect("failed to connect to websocket");
+
+                return Some((username, ws));
+            }
+            "e" => {
+                if let Err(e) = editor.download() {
+                    console_log!("failed to export map :: {:?}", e);
+                }
+            }
+            key => {
+                editor.select_brush(key);
+            }
+        }
+
+        None
+    })
+}
+
 fn from(dir: &str) -> Vector2 {
     match dir.to_uppercase().as_str() {
         "W" => Vector2 { x: 0.0, y: -1.0 },
@@ -1,15 +1,19 @@
 use app::render;
+use editor::{is_editor_page, MapEditor, EDITOR};
 use interface::{setup_canvas, setup_window_listeners};
 use std::panic;
 use utils::*;
+use viewer::{is_viewer_page, ReplayViewer, VIEWER};
 use wasm_bindgen::prelude::*;
 
 pub mod app;
 pub mod audio;
+pub mod editor;
 pub mod interface;
 pub mod login;
 pub mod socket;
 mod utils;
+pub mod viewer;
 
 #[wasm_bindgen]
 extern "C" {
@@ -24,6 +28,14 @@ extern "C" {
 pub fn start() {
     setup_logging();
 
+    if is_editor_page() {
+        EDITOR.set(Some(MapEditor::default()));
+    }
+
+    if is_viewer_page() {
+        VIEWER.set(Some(ReplayViewer::default()));
+    }
+
     let canvas_element = setup_canvas();
     setup_window_listeners();
 
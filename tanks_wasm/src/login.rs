@@ -5,7 +5,7 @@ use web_sys::{CanvasRenderingContext2d, KeyboardEvent, WebSocket};
 
 thread_local! {
   /// Editable Text for username
-  pub static USERNAME: RefCell<String> = RefCell::new(String::new());
+  pub static USERNAME: RefCell<String> = const { RefCell::new(String::new()) };
   /// Regex Match for valid usernames
   static TEXT_MATCHER: Regex = Regex::new(r"^[a-zA-Z0-9]$").unwrap();
 }
@@ -24,10 +24,11 @@ pub fn process_login_keyevent(event: KeyboardEvent) -> Option<(String, WebSocket
             "Backspace" => {
                 username.pop();
             }
-            _ => match TEXT_MATCHER.with(|re| re.is_match(&key)) {
-                true => username.push_str(&event.key()),
-                false => {}
-            },
+            _ => {
+                if TEXT_MATCHER.with(|re| re.is_match(&key)) {
+                    username.push_str(&event.key())
+                }
+            }
         }
 
         None
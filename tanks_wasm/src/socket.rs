@@ -1,4 +1,7 @@
-use crate::utils::Prepared;
+use crate::{
+    editor::{MapEditor, EDITOR},
+    utils::{js_window, Prepared},
+};
 use tanks_events::{ClientEvent, ServerEvent};
 use wasm_bindgen::{prelude::*, JsCast};
 use web_sys::{MessageEvent, WebSocket};
@@ -37,12 +40,33 @@ pub fn setup_websocket_listeners<F: Fn(ServerEvent) + 'static>(ws: &WebSocket, e
     let cloned_ws = ws.clone();
     let onopen_callback = Closure::wrap(Box::new(move |_: MessageEvent| {
         if cloned_ws.is_ready() {
-            // TEMP
-            let test_room = String::from("ASDFG");
+            // visiting the page with a mode such as `#campaign` starts a new game in that mode
+            let event = match js_window().location().hash().unwrap_or_default().as_str() {
+                "#campaign" => ClientEvent::StartCampaign,
+                "#survival" => ClientEvent::StartSurvival,
+                "#royale" => ClientEvent::StartBattleRoyale,
+                "#rotation" => ClientEvent::StartRotation,
+                "#vote" => ClientEvent::StartMapVote,
+                // test play the map that is open in the editor
+                "#editor" => ClientEvent::TestMap {
+                    map: EDITOR.with(|editor| {
+                        editor
+                            .borrow()
+                            .as_ref()
+                            .map(MapEditor::export)
+                            .unwrap_or_default()
+                    }),
+                },
+                // `#random` plays a new generated map, `#random=SEED` replays a previous one
+                hash if hash.starts_with("#random") => ClientEvent::CreateRandomSession {
+                    seed: hash.split_once('=').and_then(|(_, seed)| seed.parse().ok()),
+                },
+                // TEMP
+                _ => ClientEvent::JoinSession(String::from("ASDFG")),
+            };
+
             cloned_ws
-                .send_with_str(
-                    &serde_json::to_string(&ClientEvent::JoinSession(test_room)).unwrap(),
-                )
+                .send_with_str(&serde_json::to_string(&event).unwrap())
                 .unwrap();
         }
     }) as Box<dyn FnMut(_)>);
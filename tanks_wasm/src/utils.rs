@@ -52,7 +52,6 @@ pub fn fetch_or_create_canvas() -> web_sys::HtmlCanvasElement {
 pub trait Canvas {
     /// Sets the Element to the full size of the window
     fn set_fullscreen(&self);
-    fn add_js_listener(&self, event: &str, func: Box<dyn FnMut()>);
     fn get_2d_context(&self) -> CanvasRenderingContext2d;
 }
 
@@ -78,15 +77,6 @@ impl Canvas for HtmlCanvasElement {
         self.set_height(bounds.y as u32);
     }
 
-    fn add_js_listener(&self, event: &str, func: Box<dyn FnMut()>) {
-        let onmousemove = Closure::wrap(func);
-
-        self.add_event_listener_with_callback(event, onmousemove.as_ref().unchecked_ref())
-            .expect("added mouse listenter");
-
-        onmousemove.forget();
-    }
-
     fn get_2d_context(&self) -> CanvasRenderingContext2d {
         self.get_context("2d")
             .expect("canvas has 2d context")
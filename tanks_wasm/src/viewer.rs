@@ -0,0 +1,461 @@
+//! Replay viewer for watching recorded matches offline, opened by visiting the page with `#replay`
+//!
+//! `o` opens a `.replay` file saved by the server, which is played back by running the
+//! simulation from `tanks_core` in the page. `Space` plays and pauses, `+` and `-` change the
+//! speed, the arrow keys skip between keyframes, clicking the timeline jumps to that moment
+//! and `c` switches the camera between following each player and showing the whole map.
+
+use std::cell::RefCell;
+
+use tanks_core::{
+    common::constants::{MAP_BLOCK_HEIGHT, MAP_BLOCK_WIDTH, PLAYER_RADIUS},
+    replay::{Replay, ReplayRunner},
+    snapshot::SessionSnapshot,
+    utils::Vector2,
+};
+use tanks_events::{convert_environment_to_broadcast, convert_gamestate_to_broadcast, ServerEvent};
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{CanvasRenderingContext2d, FileReader, HtmlInputElement};
+
+use crate::{
+    app::{handle_server_event, render_overlay, render_world, ClientGameState},
+    log,
+    utils::{document, get_block_size, get_window_bounds, js_window},
+};
+
+/// Ticks between the sessions that are kept for jumping around the replay
+pub const KEYFRAME_INTERVAL: u64 = 300;
+/// Rates that the replay can be played at, relative to a live session
+const PLAYBACK_SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+/// There are 60 GameState ticks every second
+const TICKS_PER_SECOND: f64 = 60.0;
+/// Most ticks run in a single frame, so that the page never hangs after being in the background
+const MAX_TICKS_PER_FRAME: u64 = 32;
+/// Ticks played through each frame to take keyframes, so that long replays open without hanging
+const KEYFRAME_TICKS_PER_FRAME: u64 = 600;
+/// Magnification of the map while the camera follows a player
+const FOLLOW_ZOOM: f64 = 1.75;
+
+thread_local! {
+  /// The replay being watched, when the page was opened with `#replay`
+  pub static VIEWER: RefCell<Option<ReplayViewer>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+pub struct ReplayViewer {
+    /// The opened replay file, if one has been opened yet
+    pub playback: Option<Playback>,
+    /// Why the last file could not be opened
+    pub error: Option<String>,
+}
+
+pub struct Playback {
+    replay: Replay,
+    /// Sessions every [`KEYFRAME_INTERVAL`] ticks from the start of the replay
+    keyframes: Vec<SessionSnapshot>,
+    /// How far the keyframes have been taken, until the whole replay has been played through
+    keyframing: Option<SessionSnapshot>,
+    /// The session as of the moment being watched
+    session: SessionSnapshot,
+    /// What gets drawn, built from the session the same way the server builds its broadcasts
+    view: ClientGameState,
+    paused: bool,
+    /// Index into [`PLAYBACK_SPEEDS`]
+    speed: usize,
+    /// Ticks that are due to be run but have not been yet
+    pending: f64,
+    /// Time of the previous frame in milliseconds
+    last_frame: Option<f64>,
+    /// Player followed by the camera, or the whole map when there is none
+    camera: Option<String>,
+    /// Whether playing the whole replay ended in the state it was recorded with,
+    /// known once the keyframes have all been taken
+    verified: Option<bool>,
+}
+
+impl Playback {
+    /// Start watching a replay, which needs to have come from [`Replay::from_bytes`] so that it
+    /// is known to not be corrupt. The keyframes are taken a few at a time by [`Self::advance`].
+    pub fn new(replay: Replay) -> Self {
+        let mut playback = Self {
+            session: replay.initial.clone(),
+            keyframes: vec![replay.initial.clone()],
+            keyframing: Some(replay.initial.clone()),
+            replay,
+            view: ClientGameState::new(""),
+            paused: false,
+            speed: PLAYBACK_SPEEDS.iter().position(|&s| s == 1.0).unwrap(),
+            pending: 0.0,
+            last_frame: None,
+            camera: None,
+            verified: None,
+        };
+        playback.refresh_view();
+        playback
+    }
+
+    fn start_tick(&self) -> u64 {
+        self.replay.initial.gamestate.ticks
+    }
+
+    fn tick(&self) -> u64 {
+        self.session.gamestate.ticks
+    }
+
+    /// Furthest tick that can be jumped to, which is as far as the keyframes have been taken
+    fn seekable_tick(&self) -> u64 {
+        self.keyframing
+            .as_ref()
+            .map_or(self.replay.final_tick, |session| session.gamestate.ticks)
+    }
+
+    /// Play further through the replay to take more keyframes,
+    /// checking the final state once the end is reached
+    fn take_keyframes(&mut self) {
+        let Some(session) = self.keyframing.take() else {
+            return;
+        };
+
+        let start = self.start_tick();
+        let mut runner = ReplayRunner::resume(&self.replay, session);
+        for _ in 0..KEYFRAME_TICKS_PER_FRAME {
+            if runner.is_finished() {
+                self.verified =
+                    Some(runner.into_session().gamestate.checksum() == self.replay.checksum);
+                return;
+            }
+
+            runner.step();
+            if (runner.session().gamestate.ticks - start).is_multiple_of(KEYFRAME_INTERVAL) {
+                self.keyframes.push(runner.session().clone());
+            }
+        }
+        self.keyframing = Some(runner.into_session());
+    }
+
+    /// Run the ticks that are due since the previous frame
+    pub fn advance(&mut self, now: f64) {
+        self.take_keyframes();
+
+        let elapsed = self.last_frame.map_or(0.0, |last| (now - last) / 1000.0);
+        self.last_frame = Some(now);
+
+        if self.paused {
+            return;
+        }
+
+        self.pending += elapsed * TICKS_PER_SECOND * PLAYBACK_SPEEDS[self.speed];
+        let ticks = (self.pending.floor() as u64).min(MAX_TICKS_PER_FRAME);
+        self.pending = self.pending.fract();
+
+        if ticks > 0 {
+            self.run(ticks);
+        }
+    }
+
+    /// Run a number of ticks, stopping at the end of the replay
+    fn run(&mut self, ticks: u64) {
+        let mut runner = ReplayRunner::resume(&self.replay, std::mem::take(&mut self.session));
+
+        for _ in 0..ticks {
+            if runner.is_finished() {
+                self.paused = true;
+                break;
+            }
+
+            for event in runner.step() {
+                handle_server_event(ServerEvent::Mode(event), &mut self.view);
+            }
+            for damage in &runner.session().gamestate.damage_events {
+                handle_server_event(ServerEvent::Damage(damage.clone()), &mut self.view);
+            }
+        }
+
+        self.session = runner.into_session();
+        self.refresh_view();
+    }
+
+    /// Jump to a tick by playing forward from the keyframe before it,
+    /// stopping short at the moments that have not been reached by the keyframes yet
+    pub fn seek(&mut self, tick: u64) {
+        let tick = tick.clamp(self.start_tick(), self.seekable_tick());
+        let keyframe = ((tick - self.start_tick()) / KEYFRAME_INTERVAL) as usize;
+
+        self.session = self.keyframes[keyframe].clone();
+        // messages from the ruleset belong to the moment that was left
+        self.view.banner = None;
+        self.view.hud = None;
+        self.view.vote = None;
+        self.pending = 0.0;
+
+        let ticks = tick - self.tick();
+        self.run(ticks);
+    }
+
+    /// Follow the next player with the camera, going back to the whole map after the last one
+    pub fn switch_camera(&mut self) {
+        let gamestate = &self.session.gamestate;
+        let mut players = gamestate
+            .players
+            .keys()
+            .filter(|id| !gamestate.bots.contains_key(*id));
+
+        self.camera = match &self.camera {
+            None => players.next(),
+            Some(current) => players.skip_while(|id| *id != current).nth(1),
+        }
+        .cloned();
+    }
+
+    /// Show the session the same way a client in the session would see it
+    fn refresh_view(&mut self) {
+        let gamestate = &self.session.gamestate;
+        handle_server_event(
+            convert_environment_to_broadcast(&gamestate.environment),
+            &mut self.view,
+        );
+        handle_server_event(convert_gamestate_to_broadcast(gamestate), &mut self.view);
+    }
+
+    /// Fraction of the replay that has been played
+    fn progress(&self) -> f64 {
+        match self.replay.len() {
+            0 => 1.0,
+            len => (self.tick() - self.start_tick()) as f64 / len as f64,
+        }
+    }
+}
+
+impl ReplayViewer {
+    /// Handle a key that controls the playback
+    pub fn press(&mut self, key: &str) {
+        if key == "o" {
+            if let Err(e) = open_replay_file() {
+                log(&format!("failed to open replay :: {:?}", e));
+            }
+            return;
+        }
+
+        let Some(playback) = self.playback.as_mut() else {
+            return;
+        };
+
+        match key {
+            " " => {
+                // playing from the end starts the replay over
+                if playback.paused && playback.tick() == playback.replay.final_tick {
+                    playback.seek(playback.start_tick());
+                }
+                playback.paused = !playback.paused;
+            }
+            "+" | "=" => playback.speed = (playback.speed + 1).min(PLAYBACK_SPEEDS.len() - 1),
+            "-" => playback.speed = playback.speed.saturating_sub(1),
+            "ArrowRight" => playback.seek(playback.tick() + KEYFRAME_INTERVAL),
+            "ArrowLeft" => playback.seek(playback.tick().saturating_sub(KEYFRAME_INTERVAL)),
+            "c" => playback.switch_camera(),
+            _ => {}
+        }
+    }
+
+    /// Jump to the moment under the mouse when the timeline is clicked
+    pub fn click(&mut self, mouse_pos: Vector2) {
+        let Some(playback) = self.playback.as_mut() else {
+            return;
+        };
+
+        let (x, y, width, height) = timeline_bounds(get_block_size());
+        if mouse_pos.y < y - height || mouse_pos.y > y + 2.0 * height {
+            return;
+        }
+
+        let fraction = ((mouse_pos.x - x) / width).clamp(0.0, 1.0);
+        let tick = playback.start_tick() + (fraction * playback.replay.len() as f64) as u64;
+        playback.seek(tick);
+    }
+}
+
+/// Whether the page was opened to watch replays
+pub fn is_viewer_page() -> bool {
+    js_window().location().hash().unwrap_or_default() == "#replay"
+}
+
+/// Ask for a replay file and start watching it once it has been read
+fn open_replay_file() -> Result<(), JsValue> {
+    let input = document()
+        .create_element("input")?
+        .dyn_into::<HtmlInputElement>()?;
+    input.set_type("file");
+    input.set_accept(".replay");
+
+    let picker = input.clone();
+    let change_callback = Closure::wrap(Box::new(move || {
+        let Some(file) = picker.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+
+        let reader = FileReader::new().expect("file reader created");
+        let result_reader = reader.clone();
+        let load_callback = Closure::wrap(Box::new(move || {
+            let bytes = result_reader
+                .result()
+                .map(|buffer| js_sys::Uint8Array::new(&buffer).to_vec())
+                .unwrap_or_default();
+
+            VIEWER.with(|viewer| {
+                if let Some(viewer) = viewer.borrow_mut().as_mut() {
+                    match Replay::from_bytes(&bytes) {
+                        Ok(replay) => {
+                            viewer.playback = Some(Playback::new(replay));
+                            viewer.error = None;
+                        }
+                        Err(e) => viewer.error = Some(e.to_string()),
+                    }
+                }
+            });
+        }) as Box<dyn FnMut()>);
+
+        reader.set_onload(Some(load_callback.as_ref().unchecked_ref()));
+        load_callback.forget();
+        reader
+            .read_as_array_buffer(&file)
+            .expect("file could be read");
+    }) as Box<dyn FnMut()>);
+
+    input.set_onchange(Some(change_callback.as_ref().unchecked_ref()));
+    change_callback.forget();
+    input.click();
+
+    Ok(())
+}
+
+/// The (x, y, width, height) of the timeline along the bottom of the map
+fn timeline_bounds(block_size: f64) -> (f64, f64, f64, f64) {
+    (
+        block_size * 0.25,
+        block_size * (MAP_BLOCK_HEIGHT as f64 - 0.4),
+        block_size * (MAP_BLOCK_WIDTH as f64 - 0.5),
+        block_size * 0.15,
+    )
+}
+
+pub fn render_viewer(context: &CanvasRenderingContext2d, viewer: &mut ReplayViewer) {
+    let Some(playback) = viewer.playback.as_mut() else {
+        let message = match &viewer.error {
+            Some(error) => format!("{} | press o to open another replay", error),
+            None => String::from("press o to open a replay"),
+        };
+        return render_message(context, &message);
+    };
+
+    playback.advance(js_sys::Date::now());
+
+    let block_size = get_block_size();
+    let followed = playback
+        .camera
+        .as_ref()
+        .and_then(|id| playback.view.player_data.get(id))
+        .filter(|tank| tank.alive);
+
+    context.save();
+    if let Some(tank) = followed {
+        let bounds = get_window_bounds();
+        context
+            .translate(bounds.x / 2.0, bounds.y / 2.0)
+            .expect("camera could be moved");
+        context
+            .scale(FOLLOW_ZOOM, FOLLOW_ZOOM)
+            .expect("camera could be zoomed");
+        context
+            .translate(-tank.position.x, -tank.position.y)
+            .expect("camera could be moved");
+    }
+
+    render_world(context, &playback.view);
+
+    // outline the followed Tank so that it stands out from the rest
+    if let Some(tank) = followed {
+        context.set_stroke_style(&"yellow".into());
+        context.set_line_width(3.0);
+        context.begin_path();
+        context
+            .arc(
+                tank.position.x,
+                tank.position.y,
+                block_size * PLAYER_RADIUS * 2.0,
+                0.0,
+                2.0 * std::f64::consts::PI,
+            )
+            .expect("outline could not be drawn");
+        context.stroke();
+    }
+    context.restore();
+
+    render_overlay(context, &playback.view);
+    render_timeline(context, playback, block_size);
+}
+
+fn render_timeline(context: &CanvasRenderingContext2d, playback: &Playback, block_size: f64) {
+    context.save();
+
+    let (x, y, width, height) = timeline_bounds(block_size);
+
+    context.set_fill_style(&"#111".into());
+    context.fill_rect(x, y, width, height);
+    context.set_fill_style(&"#B18CFF".into());
+    context.fill_rect(x, y, width * playback.progress(), height);
+
+    // mark where the keyframes are, since those are quickest to jump to
+    context.set_fill_style(&"white".into());
+    for i in 0..playback.keyframes.len() {
+        let fraction = (i as u64 * KEYFRAME_INTERVAL) as f64 / playback.replay.len().max(1) as f64;
+        context.fill_rect(x + width * fraction, y, 2.0, height);
+    }
+
+    let seconds = |ticks: u64| {
+        let seconds = ticks / TICKS_PER_SECOND as u64;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    };
+    let camera = match &playback.camera {
+        Some(player) => format!("following {}", player),
+        None => String::from("whole map"),
+    };
+    let status = format!(
+        "{} {}x | {} / {} | {} | {}",
+        if playback.paused { "paused" } else { "playing" },
+        PLAYBACK_SPEEDS[playback.speed],
+        seconds(playback.tick() - playback.start_tick()),
+        seconds(playback.replay.len()),
+        camera,
+        match playback.verified {
+            Some(true) => "verified",
+            Some(false) => "desynced",
+            None => "verifying",
+        },
+    );
+
+    context.set_font("20px monospace");
+    context.set_text_align("left");
+    context.set_fill_style(&"white".into());
+    context
+        .fill_text(&status, x, y - height)
+        .expect("text could not be drawn");
+
+    context.restore();
+}
+
+fn render_message(context: &CanvasRenderingContext2d, message: &str) {
+    let block_size = get_block_size();
+
+    context.save();
+    context.set_font("30px monospace");
+    context.set_text_align("center");
+    context.set_fill_style(&"white".into());
+    context
+        .fill_text(
+            message,
+            block_size * MAP_BLOCK_WIDTH as f64 / 2.0,
+            block_size * MAP_BLOCK_HEIGHT as f64 / 2.0,
+        )
+        .expect("text could not be drawn");
+    context.restore();
+}